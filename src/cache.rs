@@ -0,0 +1,94 @@
+//! Binaries of single-file builds kept in `.builder-cache/`, keyed by a hash of the sources and
+//! the compile command, so switching back to an earlier version of a file skips the compiler.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const DIR: &str = ".builder-cache";
+
+/// 64-bit FNV-1a, its output never changes between builds of builder, unlike std's hasher.
+struct Fnv(u64);
+
+impl Fnv {
+	fn new() -> Fnv {
+		Fnv(0xcbf2_9ce4_8422_2325)
+	}
+
+	/// Adds `bytes` prefixed with their length, so neighbouring fields cannot run together.
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+			self.0 ^= u64::from(*byte);
+			self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+		}
+	}
+}
+
+/// Hash of the compiler, its arguments and the contents of every input.
+pub fn key(command: &Command, inputs: &[String]) -> io::Result<String> {
+	let mut hasher = Fnv::new();
+	hasher.write(command.get_program().as_encoded_bytes());
+	for arg in command.get_args() {
+		hasher.write(arg.as_encoded_bytes());
+	}
+	for input in inputs {
+		hasher.write(&std::fs::read(input)?);
+	}
+	Ok(format!("{:016x}", hasher.0))
+}
+
+fn entry(key: &str) -> PathBuf {
+	Path::new(DIR).join(key)
+}
+
+/// Copies the cached binary for `key` to `binary`, false when nothing is cached yet.
+pub fn restore(key: &str, binary: &str) -> io::Result<bool> {
+	let cached = entry(key);
+	if !cached.is_file() {
+		return Ok(false);
+	}
+	std::fs::copy(cached, binary)?;
+	Ok(true)
+}
+
+pub fn store(key: &str, binary: &str) -> io::Result<()> {
+	std::fs::create_dir_all(DIR)?;
+	std::fs::copy(binary, entry(key))?;
+	Ok(())
+}
+
+/// Stamps live in the temp directory, keyed by the binary's absolute path, so builds leave
+/// nothing behind in the project.
+fn stamp_path(binary: &str) -> PathBuf {
+	let mut hasher = Fnv::new();
+	let binary = std::env::current_dir().unwrap_or_default().join(binary);
+	hasher.write(binary.as_os_str().as_encoded_bytes());
+	std::env::temp_dir()
+		.join("builder-stamps")
+		.join(format!("{:016x}", hasher.0))
+}
+
+/// True when `binary` was last built by the command and sources hashed into `key`.
+pub fn stamped(key: &str, binary: &str) -> bool {
+	std::fs::read_to_string(stamp_path(binary)).is_ok_and(|stamp| stamp == key)
+}
+
+/// Records that `binary` now corresponds to `key`.
+pub fn stamp(key: &str, binary: &str) -> io::Result<()> {
+	let path = stamp_path(binary);
+	std::fs::create_dir_all(path.parent().unwrap())?;
+	std::fs::write(path, key)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn key_is_stable() {
+		let mut command = Command::new("gcc");
+		command.arg("main.c");
+		// keys written by earlier builds of builder must still be found
+		assert_eq!(key(&command, &[]).unwrap(), "26e2f87be4517189");
+	}
+}
@@ -0,0 +1,74 @@
+//! Optional per-project overrides read from `.builder.toml`.
+//!
+//! Every table is named after a target kind as returned by `Target::kind` (`cargo`,
+//! `make`, `cpp`, `python`, ...) and all keys are optional:
+//!
+//! ```toml
+//! [cpp]
+//! compiler = "clang++"             # compiler used instead of the default
+//! flags = ["-std=c++20", "-Wall"]  # extra arguments appended to the build command
+//! build = "make -C build"          # replaces the build command
+//! run = "./build/app"              # replaces the run command
+//! lint = "clang-tidy main.cpp"     # replaces the lint command
+//! ```
+//!
+//! Custom commands are split on whitespace, they are not passed to a shell.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::process::Command;
+
+pub const FILE_NAME: &str = ".builder.toml";
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TargetConfig {
+	pub compiler: Option<String>,
+	#[serde(default)]
+	pub flags: Vec<String>,
+	pub build: Option<String>,
+	pub run: Option<String>,
+	pub lint: Option<String>,
+}
+
+#[derive(Default, Debug)]
+pub struct Config {
+	targets: HashMap<String, TargetConfig>,
+}
+
+impl Config {
+	pub fn load() -> Result<Config, String> {
+		let content = match std::fs::read_to_string(FILE_NAME) {
+			Ok(content) => content,
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+			Err(err) => return Err(err.to_string()),
+		};
+		let targets = toml::from_str(&content).map_err(|err| err.to_string())?;
+		Ok(Config { targets })
+	}
+
+	pub fn get(&self, kind: &str) -> Option<&TargetConfig> {
+		self.targets.get(kind)
+	}
+}
+
+impl TargetConfig {
+	pub fn build_command(&self) -> Option<Command> {
+		split_command(self.build.as_deref())
+	}
+
+	pub fn run_command(&self) -> Option<Command> {
+		split_command(self.run.as_deref())
+	}
+
+	pub fn lint_command(&self) -> Option<Command> {
+		split_command(self.lint.as_deref())
+	}
+}
+
+fn split_command(line: Option<&str>) -> Option<Command> {
+	let mut words = line?.split_whitespace();
+	let mut command = Command::new(words.next()?);
+	command.args(words);
+	Some(command)
+}
@@ -0,0 +1,87 @@
+//! Hello-world templates written by `builder init <kind>`.
+
+use std::path::Path;
+
+const TEMPLATES: [(&str, &str, &str); 15] = [
+	(
+		"cpp",
+		"main.cpp",
+		"#include <iostream>\n\nint main() {\n\tstd::cout << \"Hello, world!\" << std::endl;\n\treturn 0;\n}\n",
+	),
+	(
+		"c",
+		"main.c",
+		"#include <stdio.h>\n\nint main(void) {\n\tprintf(\"Hello, world!\\n\");\n\treturn 0;\n}\n",
+	),
+	(
+		"rust",
+		"main.rs",
+		"fn main() {\n\tprintln!(\"Hello, world!\");\n}\n",
+	),
+	("js", "main.js", "console.log(\"Hello, world!\");\n"),
+	(
+		"typescript",
+		"main.ts",
+		"const greeting: string = \"Hello, world!\";\nconsole.log(greeting);\n",
+	),
+	("lua", "main.lua", "print(\"Hello, world!\")\n"),
+	(
+		"bash",
+		"main.sh",
+		"#!/usr/bin/env bash\n\necho \"Hello, world!\"\n",
+	),
+	("python", "main.py", "print(\"Hello, world!\")\n"),
+	(
+		"zig",
+		"main.zig",
+		"const std = @import(\"std\");\n\npub fn main() void {\n\tstd.debug.print(\"Hello, world!\\n\", .{});\n}\n",
+	),
+	("ruby", "main.rb", "puts \"Hello, world!\"\n"),
+	(
+		"java",
+		"Main.java",
+		"public class Main {\n\tpublic static void main(String[] args) {\n\t\tSystem.out.println(\"Hello, world!\");\n\t}\n}\n",
+	),
+	("nim", "main.nim", "echo \"Hello, world!\"\n"),
+	(
+		"asm",
+		"main.asm",
+		"global _start\n\nsection .data\nmsg: db \"Hello, world!\", 10\nlen: equ $ - msg\n\nsection .text\n_start:\n\tmov rax, 1\n\tmov rdi, 1\n\tmov rsi, msg\n\tmov rdx, len\n\tsyscall\n\tmov rax, 60\n\txor rdi, rdi\n\tsyscall\n",
+	),
+	("php", "index.php", "<?php\n\necho \"Hello, world!\\n\";\n"),
+	(
+		"go",
+		"main.go",
+		"package main\n\nimport \"fmt\"\n\nfunc main() {\n\tfmt.Println(\"Hello, world!\")\n}\n",
+	),
+];
+
+/// Build files some kinds need next to the source to be detected and built.
+const EXTRA_FILES: [(&str, &str, &str); 1] = [("go", "go.mod", "module hello\n\ngo 1.21\n")];
+
+pub fn init(kind: &str) -> Result<(), String> {
+	let template = TEMPLATES
+		.iter()
+		.find(|(name, _, _)| *name == kind)
+		.ok_or_else(|| {
+			let kinds: Vec<&str> = TEMPLATES.iter().map(|(name, _, _)| *name).collect();
+			format!(
+				"no template for {}, expected one of {}",
+				kind,
+				kinds.join(", ")
+			)
+		})?;
+	let files: Vec<_> = EXTRA_FILES
+		.iter()
+		.filter(|(name, _, _)| *name == kind)
+		.chain([template])
+		.collect();
+	if let Some((_, file, _)) = files.iter().find(|(_, file, _)| Path::new(file).exists()) {
+		return Err(format!("{} already exists", file));
+	}
+	for (_, file, body) in files {
+		std::fs::write(file, body).map_err(|err| err.to_string())?;
+		success!("==== Created {}", file);
+	}
+	Ok(())
+}
@@ -0,0 +1,1063 @@
+//! Target detection and build phases behind the `builder` command line tool.
+
+use config::TargetConfig;
+use ignore::WalkBuilder;
+use regex::Regex;
+use std::env::consts::EXE_SUFFIX;
+use std::fs::File;
+use std::io::{prelude::*, BufReader, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+pub static PREFIXES: [&str; 4] = ["main.", "Main.", "index.", "test."];
+
+pub static STD_VERSIONS: [&str; 12] = [
+	"89", "90", "98", "99", "03", "11", "14", "17", "18", "20", "23", "26",
+];
+
+/// Lint severities from most to least severe, named after shellcheck's levels.
+pub static SEVERITIES: [&str; 4] = ["error", "warning", "info", "style"];
+
+static OUTPUT_DIRS: [&str; 4] = [".", "bin", "build", "target"];
+
+pub static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--format json`, failures then go to stderr so stdout only carries the JSON lines.
+pub static JSON: AtomicBool = AtomicBool::new(false);
+
+static LAST_COMMAND: Mutex<Option<String>> = Mutex::new(None);
+
+#[doc(hidden)]
+pub const BOLD: &str = "1";
+#[doc(hidden)]
+pub const GREEN: &str = "32";
+#[doc(hidden)]
+pub const RED: &str = "31";
+
+#[macro_export]
+macro_rules! status {
+	($($arg:tt)*) => {
+		if !$crate::QUIET.load(std::sync::atomic::Ordering::Relaxed) {
+			println!($($arg)*);
+		}
+	};
+}
+
+#[macro_export]
+macro_rules! header {
+	($($arg:tt)*) => {
+		$crate::status!("{}", $crate::paint($crate::BOLD, format!($($arg)*)))
+	};
+}
+
+#[macro_export]
+macro_rules! success {
+	($($arg:tt)*) => {
+		$crate::status!("{}", $crate::paint($crate::GREEN, format!($($arg)*)))
+	};
+}
+
+/// Like `println!`, but goes to stderr with `--format json` so stdout only carries results.
+#[macro_export]
+macro_rules! echo {
+	($($arg:tt)*) => {
+		if $crate::JSON.load(std::sync::atomic::Ordering::Relaxed) {
+			eprintln!($($arg)*)
+		} else {
+			println!($($arg)*)
+		}
+	};
+}
+
+#[macro_export]
+macro_rules! failure {
+	($($arg:tt)*) => {
+		$crate::echo!("{}", $crate::paint($crate::RED, format!($($arg)*)))
+	};
+}
+
+#[doc(hidden)]
+pub fn paint(code: &str, text: String) -> String {
+	if COLOR.load(Ordering::Relaxed) {
+		format!("\x1b[{}m{}\x1b[0m", code, text)
+	} else {
+		text
+	}
+}
+
+mod cache;
+pub mod config;
+pub mod init;
+mod phase;
+pub mod watch;
+
+pub use phase::{run_phase, Opts, Phase};
+
+#[derive(PartialEq, Debug)]
+pub enum Target {
+	Cargo,
+	Make,
+	CMake,
+	Meson,
+	Go(String),
+	ZigBuild,
+	Npm,
+	Dune,
+	SwiftPackage,
+	Dub(String),
+	Stack,
+	Cabal(String),
+	Mix,
+	Gradle(String),
+	Deno(String),
+	Cpp(String),
+	C(String),
+	Rust(String),
+	Js(String),
+	TypeScript(String),
+	Lua(String),
+	Bash(String),
+	Python(String),
+	Zig(String),
+	Ruby(String),
+	Java(String),
+	Nim(String),
+	Asm(String),
+	Php(String),
+	Ocaml(String),
+	Fortran(String),
+	Swift(String),
+	Kotlin(String),
+	D(String),
+	Haskell(String),
+	Crystal(String),
+	Elixir(String),
+	R(String),
+	Vala(String),
+	Perl(String),
+	ObjC(String),
+}
+
+impl Target {
+	pub fn get_filename(&self) -> Option<String> {
+		match self {
+			Target::Bash(x)
+			| Target::Js(x)
+			| Target::TypeScript(x)
+			| Target::Cpp(x)
+			| Target::Rust(x)
+			| Target::C(x)
+			| Target::Lua(x)
+			| Target::Python(x)
+			| Target::Zig(x)
+			| Target::Ruby(x)
+			| Target::Java(x)
+			| Target::Nim(x)
+			| Target::Asm(x)
+			| Target::Php(x)
+			| Target::Ocaml(x)
+			| Target::Fortran(x)
+			| Target::Swift(x)
+			| Target::Kotlin(x)
+			| Target::D(x)
+			| Target::Haskell(x)
+			| Target::Crystal(x)
+			| Target::Elixir(x)
+			| Target::R(x)
+			| Target::Vala(x)
+			| Target::Perl(x)
+			| Target::ObjC(x) => Some(x.to_string()),
+			Target::Make => Some("Makefile".to_owned()),
+			Target::CMake => Some("CMakeLists.txt".to_owned()),
+			Target::Meson => Some("meson.build".to_owned()),
+			Target::Cargo => Some("Cargo.toml".to_owned()),
+			Target::Go(x)
+			| Target::Dub(x)
+			| Target::Cabal(x)
+			| Target::Gradle(x)
+			| Target::Deno(x) => Some(x.to_string()),
+			Target::Stack => Some("stack.yaml".to_owned()),
+			Target::Mix => Some("mix.exs".to_owned()),
+			Target::ZigBuild => Some("build.zig".to_owned()),
+			Target::Npm => Some("package.json".to_owned()),
+			Target::Dune => Some("dune-project".to_owned()),
+			Target::SwiftPackage => Some("Package.swift".to_owned()),
+		}
+	}
+	pub fn is_project(&self) -> bool {
+		matches!(
+			self,
+			Target::Make
+				| Target::CMake
+				| Target::Meson
+				| Target::Cargo
+				| Target::Go(_)
+				| Target::ZigBuild
+				| Target::Npm
+				| Target::Dune
+				| Target::SwiftPackage
+				| Target::Dub(_)
+				| Target::Stack
+				| Target::Cabal(_)
+				| Target::Mix
+				| Target::Gradle(_)
+				| Target::Deno(_)
+		)
+	}
+	/// Targets whose compiler or linter turns warnings into errors with `--werror`.
+	pub fn honours_werror(&self) -> bool {
+		matches!(
+			self,
+			Target::Cargo
+				| Target::Cpp(_)
+				| Target::C(_)
+				| Target::ObjC(_)
+				| Target::Rust(_)
+				| Target::Js(_)
+				| Target::TypeScript(_)
+		)
+	}
+	pub fn kind(&self) -> &'static str {
+		match self {
+			Target::Cargo => "cargo",
+			Target::Make => "make",
+			Target::CMake => "cmake",
+			Target::Meson => "meson",
+			Target::Go(_) => "go",
+			Target::ZigBuild | Target::Zig(_) => "zig",
+			Target::Npm => "npm",
+			Target::Dune => "dune",
+			Target::SwiftPackage => "swiftpm",
+			Target::Dub(_) => "dub",
+			Target::Stack => "stack",
+			Target::Cabal(_) => "cabal",
+			Target::Mix => "mix",
+			Target::Gradle(_) => "gradle",
+			Target::Deno(_) => "deno",
+			Target::Cpp(_) => "cpp",
+			Target::C(_) => "c",
+			Target::Rust(_) => "rust",
+			Target::Js(_) => "js",
+			Target::TypeScript(_) => "typescript",
+			Target::Lua(_) => "lua",
+			Target::Bash(_) => "bash",
+			Target::Python(_) => "python",
+			Target::Ruby(_) => "ruby",
+			Target::Java(_) => "java",
+			Target::Nim(_) => "nim",
+			Target::Asm(_) => "asm",
+			Target::Php(_) => "php",
+			Target::Ocaml(_) => "ocaml",
+			Target::Fortran(_) => "fortran",
+			Target::Swift(_) => "swift",
+			Target::Kotlin(_) => "kotlin",
+			Target::D(_) => "d",
+			Target::Haskell(_) => "haskell",
+			Target::Crystal(_) => "crystal",
+			Target::Elixir(_) => "elixir",
+			Target::R(_) => "r",
+			Target::Vala(_) => "vala",
+			Target::Perl(_) => "perl",
+			Target::ObjC(_) => "objc",
+		}
+	}
+	pub fn get_binary(&self) -> Result<Option<String>, std::io::Error> {
+		match self {
+			Target::Bash(x)
+			| Target::Js(x)
+			| Target::Lua(x)
+			| Target::Python(x)
+			| Target::Perl(x)
+			| Target::R(x)
+			| Target::Elixir(x)
+			| Target::Swift(x)
+			| Target::Php(x)
+			| Target::Ruby(x) => Ok(Some(x.to_string())),
+			Target::Cpp(x)
+			| Target::ObjC(x)
+			| Target::Vala(x)
+			| Target::Crystal(x)
+			| Target::Haskell(x)
+			| Target::D(x)
+			| Target::Fortran(x)
+			| Target::Ocaml(x)
+			| Target::Asm(x)
+			| Target::Nim(x)
+			| Target::Rust(x)
+			| Target::C(x)
+			| Target::Zig(x) => {
+				let mut bin = x.clone();
+				if let Some(idx) = bin.rfind('.') {
+					bin.truncate(idx);
+				}
+				bin.push_str(EXE_SUFFIX);
+				Ok(Some(bin))
+			}
+			Target::Java(x) => {
+				let bin = x.strip_suffix(".java").unwrap_or(x);
+				Ok(Some(bin.to_owned()))
+			}
+			Target::TypeScript(x) => {
+				let bin = x.strip_suffix(".ts").unwrap_or(x);
+				Ok(Some(format!("{}.js", bin)))
+			}
+			Target::Kotlin(x) => {
+				let bin = x.strip_suffix(".kt").unwrap_or(x);
+				Ok(Some(format!("{}.jar", bin)))
+			}
+			Target::Make => find_value(
+				"Makefile",
+				"^(?:TARGET|BIN|BINARY|EXECUTABLE|EXE|PROGRAM|PROG)\\s*[:?]?=\\s*([\\w.-]+)",
+			),
+			Target::CMake => {
+				match find_value("CMakeLists.txt", "^\\s*add_executable\\s*\\(\\s*([\\w.-]+)")? {
+					Some(name) => Ok(Some(name)),
+					None => find_value("CMakeLists.txt", "^\\s*project\\s*\\(\\s*([\\w.-]+)"),
+				}
+			}
+			Target::Meson => find_value("meson.build", "executable\\s*\\(\\s*'([\\w.-]+)'"),
+			Target::Cargo => {
+				let mut bins = cargo_bins()?;
+				Ok(if bins.len() == 1 { bins.pop() } else { None })
+			}
+			Target::Go(x) => Ok(find_value(x, "^module\\s+(\\S+)")?
+				.and_then(|x| x.rsplit('/').next().map(|x| x.to_owned()))),
+			Target::Npm => Ok(read_package()?
+				.get("name")
+				.and_then(|name| name.as_str())
+				.map(|name| name.to_owned())),
+			Target::SwiftPackage => {
+				match find_value(
+					"Package.swift",
+					"\\.executableTarget\\s*\\(\\s*name:\\s*\"([\\w.-]+)\"",
+				)? {
+					Some(name) => Ok(Some(name)),
+					None => find_value("Package.swift", "name:\\s*\"([\\w.-]+)\""),
+				}
+			}
+			Target::Dub(x) => find_value(x, "\\bname\"?\\s*:?\\s*\"([\\w.-]+)\""),
+			Target::Stack if Path::new("package.yaml").is_file() => {
+				find_value("package.yaml", "^name:\\s*([\\w.-]+)")
+			}
+			Target::Stack => Ok(std::env::current_dir()?
+				.file_name()
+				.and_then(|name| name.to_str())
+				.map(|name| name.to_owned())),
+			Target::Cabal(x) => find_value(x, "^executable\\s+([\\w.-]+)"),
+			Target::Mix => find_value("mix.exs", "\\bapp:\\s*:(\\w+)"),
+			Target::Gradle(x) => {
+				let settings = format!("settings{}", x.strip_prefix("build").unwrap_or(x));
+				if Path::new(&settings).is_file() {
+					if let Some(name) =
+						find_value(&settings, "rootProject\\.name\\s*=\\s*['\"]([\\w.-]+)")?
+					{
+						return Ok(Some(name));
+					}
+				}
+				Ok(std::env::current_dir()?
+					.file_name()
+					.and_then(|name| name.to_str())
+					.map(|name| name.to_owned()))
+			}
+			Target::Deno(x) => {
+				match find_value(x, "\"name\"\\s*:\\s*\"(?:@[\\w.-]+/)?([\\w.-]+)\"")? {
+					Some(name) => Ok(Some(format!("{}{}", name, EXE_SUFFIX))),
+					None => {
+						let entry = deno_entry();
+						let stem = entry
+							.rsplit_once('.')
+							.map_or(entry.as_str(), |(stem, _)| stem);
+						Ok(Some(format!("{}{}", stem, EXE_SUFFIX)))
+					}
+				}
+			}
+			Target::Dune => find_value("dune-project", "^\\(name\\s+([\\w.-]+)\\)"),
+			Target::ZigBuild => find_value("build.zig", "\\.name\\s*=\\s*\"([\\w.-]+)\""),
+		}
+	}
+
+	fn handle_build_result(&self, return_code: i32, warnings: usize) -> BuildOutcome {
+		if return_code != 0 {
+			return BuildOutcome::Failed(return_code);
+		}
+		match warnings {
+			0 => BuildOutcome::Success,
+			warnings => BuildOutcome::SuccessWithWarnings(warnings),
+		}
+	}
+}
+
+/// How a finished step went, warnings are counted from its stderr.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum BuildOutcome {
+	Success,
+	SuccessWithWarnings(usize),
+	Failed(i32),
+}
+
+fn uses_out_dir(target: &Target) -> bool {
+	matches!(
+		target,
+		Target::Cpp(_)
+			| Target::C(_)
+			| Target::Rust(_)
+			| Target::Zig(_)
+			| Target::Nim(_)
+			| Target::Asm(_)
+			| Target::Ocaml(_)
+			| Target::Fortran(_)
+			| Target::D(_)
+			| Target::Haskell(_)
+			| Target::Crystal(_)
+			| Target::Vala(_)
+			| Target::ObjC(_)
+	)
+}
+
+fn output_binary(target: &Target, opts: &Opts) -> Result<Option<String>, std::io::Error> {
+	if let (Target::Cpp(_) | Target::C(_) | Target::Rust(_), Some(output)) = (target, &opts.output)
+	{
+		return Ok(Some(output.clone()));
+	}
+	let binary = target.get_binary()?;
+	match (&opts.out_dir, binary) {
+		(Some(dir), Some(binary)) if uses_out_dir(target) => {
+			let name = Path::new(&binary).file_name().unwrap_or_default();
+			Ok(Some(
+				Path::new(dir).join(name).to_string_lossy().into_owned(),
+			))
+		}
+		(_, binary) => Ok(binary),
+	}
+}
+
+/// The binary `target` builds, None when it has none or its build file cannot be read, which
+/// `run_phase` reports before any phase starts.
+pub fn resolve_binary(target: &Target, opts: &Opts) -> Option<String> {
+	output_binary(target, opts).ok().flatten()
+}
+
+/// Finds binary in one of the output directories, also trying the platform executable suffix
+/// for names taken from build files.
+fn locate_binary(binary: &str) -> Option<PathBuf> {
+	let names = [binary.to_owned(), format!("{}{}", binary, EXE_SUFFIX)];
+	OUTPUT_DIRS
+		.iter()
+		.flat_map(|dir| names.iter().map(move |name| Path::new(dir).join(name)))
+		.find(|path| path.is_file())
+}
+
+fn find_value(file_name: &str, pattern: &str) -> Result<Option<String>, std::io::Error> {
+	let file = File::open(file_name)?;
+	let lines = BufReader::new(file).lines();
+	let target = Regex::new(pattern).expect("Regex error");
+
+	for line in lines {
+		let line = line?;
+		let mat = target.captures(&line);
+		if let Some(mat) = mat {
+			let val = mat.get(1);
+			if let Some(val) = val {
+				return Ok(Some(val.as_str().to_owned()));
+			}
+		}
+	}
+	Ok(None)
+}
+
+/// Entry file of a Deno project, main.ts unless only another common entry exists.
+pub(crate) fn deno_entry() -> String {
+	["main.ts", "main.js", "mod.ts", "index.ts", "index.js"]
+		.into_iter()
+		.find(|name| Path::new(name).is_file())
+		.unwrap_or("main.ts")
+		.to_owned()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+	use std::os::unix::fs::PermissionsExt;
+	path.metadata()
+		.is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+	path.is_file()
+}
+
+/// The project's Gradle wrapper when it ships an executable one, the system gradle otherwise.
+fn gradle() -> Command {
+	let wrapper = Path::new(if cfg!(windows) {
+		"gradlew.bat"
+	} else {
+		"./gradlew"
+	});
+	if is_executable(wrapper) {
+		Command::new(wrapper)
+	} else {
+		Command::new("gradle")
+	}
+}
+
+fn read_package() -> Result<serde_json::Value, std::io::Error> {
+	let content = std::fs::read_to_string("package.json")?;
+	Ok(serde_json::from_str(&content)?)
+}
+
+fn cargo_bins() -> Result<Vec<String>, std::io::Error> {
+	let content = std::fs::read_to_string("Cargo.toml")?;
+	let manifest: toml::Table = toml::from_str(&content)
+		.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+	let name = |table: &toml::Value| table.get("name")?.as_str().map(|name| name.to_owned());
+
+	let bins: Vec<String> = manifest
+		.get("bin")
+		.and_then(|bins| bins.as_array())
+		.map(|bins| bins.iter().filter_map(name).collect())
+		.unwrap_or_default();
+	if bins.is_empty() {
+		return Ok(manifest.get("package").and_then(name).into_iter().collect());
+	}
+	Ok(bins)
+}
+
+fn npm_script(name: &str) -> Option<Command> {
+	let package = read_package().ok()?;
+	package.get("scripts")?.get(name)?;
+	let mut command = Command::new("npm");
+	match name {
+		"start" | "test" => command.arg(name),
+		_ => command.arg("run").arg(name),
+	};
+	Some(command)
+}
+
+pub fn update_target(old_target: Option<Target>, new_target: Option<Target>) -> Option<Target> {
+	match (old_target, new_target) {
+		(Some(Target::Make), _) => Some(Target::Make),
+		(_, Some(Target::Make)) => Some(Target::Make),
+		(Some(Target::CMake), _) => Some(Target::CMake),
+		(_, Some(Target::CMake)) => Some(Target::CMake),
+		(Some(Target::Meson), _) => Some(Target::Meson),
+		(_, Some(Target::Meson)) => Some(Target::Meson),
+		(Some(Target::Cargo), _) => Some(Target::Cargo),
+		(_, Some(Target::Cargo)) => Some(Target::Cargo),
+		(Some(Target::Dune), _) => Some(Target::Dune),
+		(_, Some(Target::Dune)) => Some(Target::Dune),
+		(Some(Target::SwiftPackage), _) => Some(Target::SwiftPackage),
+		(_, Some(Target::SwiftPackage)) => Some(Target::SwiftPackage),
+		(Some(Target::Dub(x)), _) => Some(Target::Dub(x)),
+		(_, Some(Target::Dub(x))) => Some(Target::Dub(x)),
+		(Some(Target::Stack), _) => Some(Target::Stack),
+		(_, Some(Target::Stack)) => Some(Target::Stack),
+		(Some(Target::Cabal(x)), _) => Some(Target::Cabal(x)),
+		(_, Some(Target::Cabal(x))) => Some(Target::Cabal(x)),
+		(Some(Target::Mix), _) => Some(Target::Mix),
+		(_, Some(Target::Mix)) => Some(Target::Mix),
+		(Some(Target::Gradle(x)), _) => Some(Target::Gradle(x)),
+		(_, Some(Target::Gradle(x))) => Some(Target::Gradle(x)),
+		(Some(Target::Go(x)), _) => Some(Target::Go(x)),
+		(_, Some(Target::Go(x))) => Some(Target::Go(x)),
+		(Some(Target::ZigBuild), _) => Some(Target::ZigBuild),
+		(_, Some(Target::ZigBuild)) => Some(Target::ZigBuild),
+		(Some(Target::Deno(x)), _) => Some(Target::Deno(x)),
+		(_, Some(Target::Deno(x))) => Some(Target::Deno(x)),
+		(Some(Target::Npm), _) => Some(Target::Npm),
+		(_, Some(Target::Npm)) => Some(Target::Npm),
+		(_, Some(x)) => Some(x),
+		(x, None) => x,
+	}
+}
+
+pub fn endings(file_name: &str) -> Option<Target> {
+	if file_name.ends_with(".js") {
+		return Some(Target::Js(file_name.to_string()));
+	} else if file_name.ends_with(".ts") {
+		return Some(Target::TypeScript(file_name.to_string()));
+	} else if file_name.ends_with(".cpp") || file_name.ends_with(".cxx") {
+		return Some(Target::Cpp(file_name.to_string()));
+	} else if file_name.ends_with(".lua") {
+		return Some(Target::Lua(file_name.to_string()));
+	} else if file_name.ends_with(".bash") || file_name.ends_with(".sh") {
+		return Some(Target::Bash(file_name.to_string()));
+	} else if file_name.ends_with(".rs") {
+		return Some(Target::Rust(file_name.to_string()));
+	} else if file_name.ends_with(".c") {
+		return Some(Target::C(file_name.to_string()));
+	} else if file_name.ends_with(".py") {
+		return Some(Target::Python(file_name.to_string()));
+	} else if file_name.ends_with(".zig") {
+		return Some(Target::Zig(file_name.to_string()));
+	} else if file_name.ends_with(".rb") {
+		return Some(Target::Ruby(file_name.to_string()));
+	} else if file_name.ends_with(".java") {
+		return Some(Target::Java(file_name.to_string()));
+	} else if file_name.ends_with(".nim") {
+		return Some(Target::Nim(file_name.to_string()));
+	} else if file_name.ends_with(".asm") || file_name.ends_with(".s") {
+		return Some(Target::Asm(file_name.to_string()));
+	} else if file_name.ends_with(".php") {
+		return Some(Target::Php(file_name.to_string()));
+	} else if file_name.ends_with(".ml") {
+		return Some(Target::Ocaml(file_name.to_string()));
+	} else if file_name.ends_with(".f90") || file_name.ends_with(".f95") {
+		return Some(Target::Fortran(file_name.to_string()));
+	} else if file_name.ends_with(".swift") {
+		return Some(Target::Swift(file_name.to_string()));
+	} else if file_name.ends_with(".kt") {
+		return Some(Target::Kotlin(file_name.to_string()));
+	} else if file_name.ends_with(".d") {
+		return Some(Target::D(file_name.to_string()));
+	} else if file_name.ends_with(".hs") {
+		return Some(Target::Haskell(file_name.to_string()));
+	} else if file_name.ends_with(".cr") {
+		return Some(Target::Crystal(file_name.to_string()));
+	} else if file_name.ends_with(".exs") || file_name.ends_with(".ex") {
+		return Some(Target::Elixir(file_name.to_string()));
+	} else if file_name.ends_with(".R") || file_name.ends_with(".r") {
+		return Some(Target::R(file_name.to_string()));
+	} else if file_name.ends_with(".vala") {
+		return Some(Target::Vala(file_name.to_string()));
+	} else if file_name.ends_with(".pl") {
+		return Some(Target::Perl(file_name.to_string()));
+	} else if file_name.ends_with(".m") {
+		return Some(Target::ObjC(file_name.to_string()));
+	}
+	None
+}
+
+/// Detects the target of the current directory. Targets name their files relative to it and
+/// every phase reads and builds there, so change into a project before detecting it.
+pub fn detect_target() -> Option<Target> {
+	let prefixes: Vec<String> = PREFIXES.iter().map(|prefix| prefix.to_string()).collect();
+	detect(Path::new("."), &prefixes, None, false)
+		.ok()
+		.flatten()
+}
+
+/// Every target found in dir in alphabetical order, before the priority rules pick one.
+pub fn detect_candidates(
+	dir: &Path,
+	prefixes: &[String],
+	forced: Option<&str>,
+	respect_gitignore: bool,
+) -> Result<Vec<Target>, std::io::Error> {
+	let mut entries: Vec<_> = std::fs::read_dir(dir)?
+		.flatten()
+		.map(|entry| entry.file_name())
+		.collect();
+	if respect_gitignore {
+		let kept: Vec<_> = WalkBuilder::new(dir)
+			.max_depth(Some(1))
+			.hidden(false)
+			.require_git(false)
+			.build()
+			.flatten()
+			.filter(|entry| entry.depth() == 1)
+			.map(|entry| entry.file_name().to_owned())
+			.collect();
+		entries.retain(|entry| kept.contains(entry));
+	}
+	entries.sort();
+
+	let mut candidates = Vec::new();
+	for entry in &entries {
+		if let Some(entry) = entry.to_str() {
+			let candidate = match entry {
+				"Makefile" => Some(Target::Make),
+				"CMakeLists.txt" => Some(Target::CMake),
+				"meson.build" => Some(Target::Meson),
+				"Cargo.toml" => Some(Target::Cargo),
+				"build.zig" => Some(Target::ZigBuild),
+				"dune-project" => Some(Target::Dune),
+				"Package.swift" => Some(Target::SwiftPackage),
+				"dub.json" | "dub.sdl" => Some(Target::Dub(entry.to_string())),
+				"stack.yaml" => Some(Target::Stack),
+				"mix.exs" => Some(Target::Mix),
+				"build.gradle" | "build.gradle.kts" => Some(Target::Gradle(entry.to_string())),
+				_ if entry.ends_with(".cabal") => Some(Target::Cabal(entry.to_string())),
+				"deno.json" | "deno.jsonc" => Some(Target::Deno(entry.to_string())),
+				"package.json" => Some(Target::Npm),
+				"go.mod" => Some(Target::Go(entry.to_string())),
+				_ if prefixes
+					.iter()
+					.any(|prefix| entry.starts_with(prefix.as_str())) =>
+				{
+					endings(entry)
+				}
+				_ => None,
+			};
+			if let Some(kind) = forced {
+				if candidate.as_ref().map(Target::kind) != Some(kind) {
+					continue;
+				}
+			}
+			candidates.extend(candidate);
+		}
+	}
+	Ok(candidates)
+}
+
+pub fn detect(
+	dir: &Path,
+	prefixes: &[String],
+	forced: Option<&str>,
+	respect_gitignore: bool,
+) -> Result<Option<Target>, std::io::Error> {
+	let mut target = None;
+	let mut files = Vec::new();
+	for candidate in detect_candidates(dir, prefixes, forced, respect_gitignore)? {
+		if candidate.is_project() {
+			target = update_target(target, Some(candidate));
+		} else {
+			files.push(candidate);
+		}
+	}
+
+	// test.* only becomes the target when there is nothing else, otherwise it is run by `test`
+	let is_test = |file: &Target| {
+		file.get_filename()
+			.is_some_and(|name| name.starts_with("test."))
+	};
+	if files.iter().any(|file| !is_test(file)) {
+		files.retain(|file| !is_test(file));
+	}
+	if target.is_none() && files.len() > 1 {
+		let names: Vec<String> = files.iter().filter_map(Target::get_filename).collect();
+		status!(
+			"==== Multiple targets found: {} — using {} (override with --target)",
+			names.join(", "),
+			names[0]
+		);
+	}
+	if target.is_none() {
+		target = files.into_iter().next();
+	}
+	Ok(target)
+}
+
+pub fn find_entry(dir: &Path, depth: usize, prefixes: &[String]) -> Option<Target> {
+	let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+		.ok()?
+		.flatten()
+		.map(|entry| entry.path())
+		.collect();
+	paths.sort();
+
+	for path in paths.iter().filter(|path| path.is_file()) {
+		let name = path.file_name()?.to_str()?;
+		if prefixes
+			.iter()
+			.any(|prefix| name.starts_with(prefix.as_str()))
+		{
+			let path = path.strip_prefix(".").unwrap_or(path);
+			if let Some(target) = path.to_str().and_then(endings) {
+				return Some(target);
+			}
+		}
+	}
+
+	if depth == 0 {
+		return None;
+	}
+	for path in paths.iter().filter(|path| path.is_dir()) {
+		match path.file_name().and_then(|name| name.to_str()) {
+			Some("target") | Some("node_modules") | Some(".git") => continue,
+			_ => {
+				if let Some(target) = find_entry(path, depth - 1, prefixes) {
+					return Some(target);
+				}
+			}
+		}
+	}
+	None
+}
+
+/// True when binary exists and is newer than every input.
+fn up_to_date(binary: &str, inputs: &[String]) -> bool {
+	let modified = |path: &str| {
+		std::fs::metadata(path)
+			.and_then(|meta| meta.modified())
+			.ok()
+	};
+	let Some(built) = modified(binary) else {
+		return false;
+	};
+	inputs
+		.iter()
+		.all(|input| modified(input).is_some_and(|time| time < built))
+}
+
+/// Files next to `entry` with one of `extensions`, `test.*` files have their own `main` and are
+/// left out unless they are the entry.
+fn sources(entry: &str, extensions: &[&str]) -> Vec<String> {
+	let dir = Path::new(entry).parent().unwrap_or(Path::new(""));
+	let entry_name = Path::new(entry).file_name().and_then(|name| name.to_str());
+	let read = if dir.as_os_str().is_empty() {
+		std::fs::read_dir(".")
+	} else {
+		std::fs::read_dir(dir)
+	};
+	let mut files: Vec<String> = match read {
+		Ok(entries) => entries
+			.flatten()
+			.filter_map(|entry| entry.file_name().into_string().ok())
+			.filter(|name| extensions.iter().any(|ext| name.ends_with(ext)))
+			.filter(|name| Some(name.as_str()) == entry_name || !name.starts_with("test."))
+			.map(|name| dir.join(name).to_string_lossy().into_owned())
+			.collect(),
+		Err(_) => Vec::new(),
+	};
+	if files.is_empty() {
+		files.push(entry.to_owned());
+	}
+	files.sort();
+	files
+}
+
+/// Every `.rs` file below the directory of `entry`, so edits to `mod` files are noticed.
+fn modules(entry: &str) -> Vec<String> {
+	fn walk(dir: &Path, files: &mut Vec<String>) {
+		let Ok(entries) = std::fs::read_dir(dir) else {
+			return;
+		};
+		for path in entries.flatten().map(|entry| entry.path()) {
+			match path.file_name().and_then(|name| name.to_str()) {
+				Some("target") | Some("node_modules") | Some(".git") => continue,
+				_ if path.is_dir() => walk(&path, files),
+				_ if path.extension().is_some_and(|ext| ext == "rs") => {
+					files.push(path.to_string_lossy().into_owned())
+				}
+				_ => {}
+			}
+		}
+	}
+	let dir = Path::new(entry)
+		.parent()
+		.filter(|dir| !dir.as_os_str().is_empty())
+		.unwrap_or(Path::new("."));
+	let mut files = Vec::new();
+	walk(dir, &mut files);
+	if files.is_empty() {
+		files.push(entry.to_owned());
+	}
+	files.sort();
+	files
+}
+
+fn env_or(name: &str, default: &str) -> String {
+	match std::env::var(name) {
+		Ok(val) if !val.trim().is_empty() => val,
+		_ => default.to_owned(),
+	}
+}
+
+fn env_flags(name: &str) -> Vec<String> {
+	std::env::var(name)
+		.map(|val| val.split_whitespace().map(|flag| flag.to_owned()).collect())
+		.unwrap_or_default()
+}
+
+fn compiler(settings: Option<&TargetConfig>, var: &str, default: &str) -> String {
+	env_or(
+		var,
+		settings
+			.and_then(|settings| settings.compiler.as_deref())
+			.unwrap_or(default),
+	)
+}
+
+fn command_line(command: &Command) -> String {
+	let mut line = command.get_program().to_string_lossy().into_owned();
+	for arg in command.get_args() {
+		line.push(' ');
+		line.push_str(&arg.to_string_lossy());
+	}
+	line
+}
+
+fn print_command(command: &Command) {
+	echo!("==== exec: {}", command_line(command));
+}
+
+/// Sends the stdout of a child to stderr with `--format json`.
+fn json_stdout(command: &mut Command) {
+	if JSON.load(Ordering::Relaxed) {
+		command.stdout(std::io::stderr());
+	}
+}
+
+/// Remembers the main command of the running phase for `take_command`.
+fn record_command(command: &Command) {
+	*LAST_COMMAND.lock().unwrap() = Some(command_line(command));
+}
+
+/// The main command the last phase ran, None when it did not run one.
+pub fn take_command() -> Option<String> {
+	LAST_COMMAND.lock().unwrap().take()
+}
+
+fn remove_artifact(path: &str, dry_run: bool) {
+	if dry_run {
+		echo!("==== Would remove {}", path);
+		return;
+	}
+	let removed = if Path::new(path).is_dir() {
+		std::fs::remove_dir_all(path)
+	} else {
+		std::fs::remove_file(path)
+	};
+	match removed {
+		Ok(()) => success!("==== Removed {}", path),
+		Err(err) if err.kind() == ErrorKind::NotFound => {
+			status!("==== Nothing to clean ({})", path)
+		}
+		Err(err) => failure!("==== Failed to remove {} ({})", path, err),
+	}
+}
+
+/// Exit code of a finished child, children killed by a signal report 128 + the signal number.
+fn status_code(status: ExitStatus) -> i32 {
+	if let Some(code) = status.code() {
+		return code;
+	}
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::ExitStatusExt;
+		if let Some(signal) = status.signal() {
+			failure!("==== Program terminated by signal {}", signal);
+			return 128 + signal;
+		}
+	}
+	failure!("==== Program terminated by signal");
+	137
+}
+
+fn run_step(mut command: Command, verbose: bool, dry_run: bool) -> i32 {
+	if verbose || dry_run {
+		print_command(&command);
+	}
+	if dry_run {
+		return 0;
+	}
+	json_stdout(&mut command);
+	match command.status() {
+		Ok(status) => status_code(status),
+		Err(err) => {
+			spawn_failed(&command, &err, "setup");
+			127
+		}
+	}
+}
+
+fn spawn_failed(command: &Command, err: &std::io::Error, what: &str) {
+	if err.kind() == ErrorKind::NotFound {
+		failure!(
+			"==== '{}' not found in PATH — is it installed?",
+			command.get_program().to_string_lossy()
+		);
+	} else {
+		failure!("==== Failed to run {} command ({})", what, err);
+	}
+}
+
+/// Kills `child` together with every process below it, which would keep running otherwise.
+fn kill_tree(child: &mut Child) {
+	#[cfg(unix)]
+	{
+		let mut pids = vec![child.id()];
+		if let Ok(output) = Command::new("ps")
+			.args(["-A", "-o", "pid=", "-o", "ppid="])
+			.output()
+		{
+			let parents: Vec<(u32, u32)> = String::from_utf8_lossy(&output.stdout)
+				.lines()
+				.filter_map(|line| {
+					let mut fields = line.split_whitespace();
+					Some((fields.next()?.parse().ok()?, fields.next()?.parse().ok()?))
+				})
+				.collect();
+			let mut next = 0;
+			while let Some(&parent) = pids.get(next) {
+				pids.extend(
+					parents
+						.iter()
+						.filter(|(_, ppid)| *ppid == parent)
+						.map(|(pid, _)| *pid),
+				);
+				next += 1;
+			}
+		}
+		let _ = Command::new("kill")
+			.args(["-s", "KILL"])
+			.args(pids.iter().map(u32::to_string))
+			.stderr(Stdio::null())
+			.status();
+	}
+	let _ = child.kill();
+	let _ = child.wait();
+}
+
+fn has_command(name: &str) -> bool {
+	Command::new(name)
+		.arg("--version")
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.status()
+		.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn binary(target: Target) -> String {
+		target.get_binary().unwrap().unwrap()
+	}
+
+	#[test]
+	fn binary_of_dotless_file_is_the_file_name() {
+		assert_eq!(
+			binary(Target::C("main".to_owned())),
+			format!("main{}", EXE_SUFFIX)
+		);
+	}
+
+	#[test]
+	fn binary_strips_only_the_last_extension() {
+		assert_eq!(
+			binary(Target::Cpp("foo.bar.cpp".to_owned())),
+			format!("foo.bar{}", EXE_SUFFIX)
+		);
+		assert_eq!(
+			binary(Target::C("foo.c".to_owned())),
+			format!("foo{}", EXE_SUFFIX)
+		);
+		assert_eq!(
+			binary(Target::Cpp("foo".to_owned())),
+			format!("foo{}", EXE_SUFFIX)
+		);
+	}
+
+	#[test]
+	fn detect_picks_the_same_of_two_candidates() {
+		let dir = std::env::temp_dir().join(format!("builder-detect-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(&dir).unwrap();
+		std::fs::write(dir.join("main.cpp"), "int main() {}\n").unwrap();
+		std::fs::write(dir.join("main.c"), "int main() {}\n").unwrap();
+		let prefixes: Vec<String> = PREFIXES.iter().map(|prefix| prefix.to_string()).collect();
+		for _ in 0..3 {
+			assert_eq!(
+				detect(&dir, &prefixes, None, false).unwrap(),
+				Some(Target::C("main.c".to_owned()))
+			);
+		}
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -1,369 +1,565 @@
-use regex::Regex;
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
+use builder::config::{self, Config};
+use builder::{
+	detect, detect_candidates, endings, failure, find_entry, header, init, resolve_binary,
+	run_phase, status, success, take_command, watch, Opts, Phase, Target, COLOR, JSON, PREFIXES,
+	QUIET, SEVERITIES, STD_VERSIONS,
+};
+use serde::Serialize;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::{self, Command};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Instant;
 
-#[derive(PartialEq, Debug)]
-enum Target {
-	Cargo,
-	Make,
-	Cpp(String),
-	C(String),
-	Rust(String),
-	Js(String),
-	Lua(String),
-	Bash(String),
+/// One line of `--format json` output.
+#[derive(Serialize)]
+struct PhaseResult {
+	phase: &'static str,
+	target: &'static str,
+	command: Option<String>,
+	exit_code: i32,
+	duration_ms: u64,
 }
 
-impl Target {
-	fn get_filename(&self) -> Option<String> {
-		match self {
-			Target::Bash(x)
-			| Target::Js(x)
-			| Target::Cpp(x)
-			| Target::Rust(x)
-			| Target::C(x)
-			| Target::Lua(x) => Some(x.to_string()),
-			Target::Make => Some("Makefile".to_owned()),
-			Target::Cargo => Some("Cargo.toml".to_owned()),
-		}
+fn env_pair(line: &str) -> Option<(String, String)> {
+	let (key, value) = line.split_once('=')?;
+	if key.trim().is_empty() {
+		return None;
 	}
-	fn get_binary(&self) -> Option<String> {
-		match self {
-			Target::Bash(x) | Target::Js(x) | Target::Lua(x) => Some(x.to_string()),
-			Target::Cpp(x) | Target::Rust(x) | Target::C(x) => {
-				let mut bin = x.clone();
-				bin.truncate(bin.find(".").unwrap());
-				Some(bin)
-			}
-			Target::Make => {
-				let file = File::open("Makefile").unwrap();
-				let lines = BufReader::new(file).lines();
-				let target = Regex::new("^TARGET\\s*:=\\s*(\\w+)").expect("Regex error");
+	Some((key.trim().to_owned(), value.trim().to_owned()))
+}
 
-				for line in lines.into_iter().flatten() {
-					let mat = target.captures(&line);
-					if let Some(mat) = mat {
-						let val = mat.get(1);
-						if let Some(val) = val {
-							return Some(val.as_str().to_owned());
-						}
-					}
-				}
-				None
-			}
-			Target::Cargo => {
-				let file = File::open("Cargo.toml").unwrap();
-				let lines = BufReader::new(file).lines();
-				let target = Regex::new("^name\\s*=\\s*\"(\\w+)\"").expect("Regex error");
+fn build_all(opts: &mut Opts, prefixes: &[String], respect_gitignore: bool) -> i32 {
+	let mut dirs: Vec<PathBuf> = match std::fs::read_dir(".") {
+		Ok(dir) => dir
+			.flatten()
+			.map(|entry| entry.path())
+			.filter(|path| path.is_dir())
+			.collect(),
+		Err(err) => {
+			failure!("==== Cannot read current directory: {}", err);
+			return 2;
+		}
+	};
+	dirs.sort();
 
-				for line in lines.into_iter().flatten() {
-					let mat = target.captures(&line);
-					if let Some(mat) = mat {
-						let val = mat.get(1);
-						if let Some(val) = val {
-							return Some(val.as_str().to_owned());
-						}
-					}
-				}
-				None
+	let root = match std::env::current_dir() {
+		Ok(root) => root,
+		Err(err) => {
+			failure!("==== Cannot read current directory: {}", err);
+			return 2;
+		}
+	};
+	let mut failed = Vec::new();
+	let mut built = 0;
+	for dir in dirs {
+		let name = dir
+			.file_name()
+			.and_then(|name| name.to_str())
+			.unwrap_or_default();
+		if name.starts_with('.') || name == "target" || name == "node_modules" {
+			continue;
+		}
+		let Ok(Some(target)) = detect(&dir, prefixes, None, respect_gitignore) else {
+			continue;
+		};
+		header!("==== Directory {}", name);
+		if let Err(err) = std::env::set_current_dir(&dir) {
+			failure!("==== Cannot change to directory {} ({})", name, err);
+			failed.push(name.to_owned());
+			continue;
+		}
+		let code = match Config::load() {
+			Ok(config) => {
+				opts.config = config;
+				run_phase(&target, Phase::Build, opts)
+			}
+			Err(err) => {
+				failure!("==== Invalid {}\n{}", config::FILE_NAME, err);
+				2
 			}
+		};
+		if let Err(err) = std::env::set_current_dir(&root) {
+			failure!("==== Cannot return to {} ({})", root.display(), err);
+			return 2;
 		}
+		built += 1;
+		if code != 0 {
+			failed.push(name.to_owned());
+		}
+	}
+
+	if failed.is_empty() {
+		success!("==== {} of {} projects built", built, built);
+		0
+	} else {
+		failure!(
+			"==== {} of {} projects failed ({})",
+			failed.len(),
+			built,
+			failed.join(", ")
+		);
+		1
 	}
+}
 
-	fn handle_build_result(&self, return_code: i32, _stdout: Option<u8>) -> bool {
-		if return_code != 0 {
-			return false;
+/// Runs a `--pre`/`--post` hook through the shell and returns its exit code.
+fn hook(name: &str, line: &str, exit_code: i32, dry_run: bool) -> i32 {
+	header!("==== {} hook ({})", name, line);
+	if dry_run {
+		return 0;
+	}
+	let mut command = if cfg!(windows) {
+		let mut command = Command::new("cmd");
+		command.arg("/C");
+		command
+	} else {
+		let mut command = Command::new("sh");
+		command.arg("-c");
+		command
+	};
+	command
+		.arg(line)
+		.env("BUILDER_EXIT_CODE", exit_code.to_string());
+	if JSON.load(Ordering::Relaxed) {
+		command.stdout(std::io::stderr());
+	}
+	match command.status() {
+		Ok(status) => status.code().unwrap_or(1),
+		Err(err) => {
+			failure!("==== Could not run {} hook ({})", name, err);
+			127
 		}
-		true
 	}
 }
 
-fn update_target(old_target: Option<Target>, new_target: Option<Target>) -> Option<Target> {
-	match (old_target, new_target) {
-		(Some(Target::Make), _) => Some(Target::Make),
-		(_, Some(Target::Make)) => Some(Target::Make),
-		(Some(Target::Cargo), _) => Some(Target::Cargo),
-		(_, Some(Target::Cargo)) => Some(Target::Cargo),
-		(_, Some(x)) => Some(x),
-		(_, _) => None,
+/// Runs the `--post` hook, if any, and returns the exit code builder should finish with.
+fn post_hook(line: Option<&str>, exit_code: i32, dry_run: bool) -> i32 {
+	let Some(line) = line else {
+		return exit_code;
+	};
+	let code = hook("Post", line, exit_code, dry_run);
+	if code != 0 {
+		failure!("==== Post hook failed [{}]", code);
+		if exit_code == 0 {
+			return code;
+		}
 	}
+	exit_code
 }
 
-fn endings(file_name: &str) -> Option<Target> {
-	if file_name.ends_with(".js") {
-		return Some(Target::Js(file_name.to_string()));
-	} else if file_name.ends_with(".cpp") || file_name.ends_with(".cxx") {
-		return Some(Target::Cpp(file_name.to_string()));
-	} else if file_name.ends_with(".lua") {
-		return Some(Target::Lua(file_name.to_string()));
-	} else if file_name.ends_with(".bash") || file_name.ends_with(".sh") {
-		return Some(Target::Bash(file_name.to_string()));
-	} else if file_name.ends_with(".rs") {
-		return Some(Target::Rust(file_name.to_string()));
-	} else if file_name.ends_with(".c") {
-		return Some(Target::C(file_name.to_string()));
-	}
-	None
+fn ask(question: &str) -> bool {
+	if JSON.load(Ordering::Relaxed) {
+		eprint!("{}", question);
+	} else {
+		print!("{}", question);
+		let _ = std::io::stdout().flush();
+	}
+	let mut answer = String::new();
+	std::io::stdin().read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y")
 }
 
-fn main() -> Result<(), Box<(dyn std::error::Error + 'static)>> {
+fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
 	let mut target = None;
+	let mut opts = Opts::default();
 
 	let mut run = false;
 	let mut build = false;
-	let mut release = false;
 	let mut lint = false;
+	let mut check = false;
+	let mut test = false;
+	let mut clean = false;
+	let mut clean_first = false;
+	let mut recursive = false;
+	let mut entry_name = None;
+	let mut watch = false;
+	let mut install = false;
+	let mut doc = false;
+	let mut color = true;
+	let mut forced = None;
+	let mut bench = false;
+	let mut init = None;
+	let mut directory = None;
+	let mut env = Vec::new();
+	let mut env_file = None;
+	let mut all = false;
+	let mut confirm = false;
+	let mut respect_gitignore = false;
+	let mut json = false;
+	let mut pre = None;
+	let mut post = None;
+	let mut exit_code = 0;
 
-	for arg in std::env::args() {
+	let mut args = std::env::args().peekable();
+	while let Some(arg) = args.next() {
 		match arg.as_str() {
-			"build" => build = true,
-			"run" => run = true,
-			"release" => release = true,
-			"lint" => lint = true,
-			_ => continue,
-		}
-	}
-
-	for entry in std::fs::read_dir(".").expect("Faild to read dir") {
-		let entry = entry?.file_name();
-
-		if let Some(entry) = entry.to_str() {
-			match entry {
-				"Makefile" => {
-					target = update_target(target, Some(Target::Make));
-					break;
+			"build" | "--build" => build = true,
+			"run" | "--run" => run = true,
+			"release" | "--release" => opts.release = true,
+			"lint" | "--lint" => lint = true,
+			"check" => check = true,
+			"test" => test = true,
+			"clean" => clean = true,
+			"--clean-first" => clean_first = true,
+			"watch" => watch = true,
+			"install" => install = true,
+			"doc" => doc = true,
+			"bench" => bench = true,
+			"init" => init = args.next(),
+			"--bench" => opts.bench_name = args.next(),
+			"--features" => {
+				if let Some(list) = args.next() {
+					opts.features.push("--features".to_owned());
+					opts.features.push(list);
 				}
-				"Cargo.toml" => target = update_target(target, Some(Target::Cargo)),
+			}
+			"--all-features" | "--no-default-features" => opts.features.push(arg),
+			"--link" | "-l" => opts.links.extend(args.next()),
+			"--lib-path" | "-L" => opts.lib_paths.extend(args.next()),
+			"--pkg" => opts.pkgs.extend(args.next()),
+			"--framework" => opts.frameworks.extend(args.next()),
+			"--flag" | "-X" => opts.flags.extend(args.next()),
+			"--open" => opts.open = true,
+			"--verbose" | "-v" => opts.verbose = true,
+			"--fmt" => opts.fmt = true,
+			"--fix" => opts.fix = true,
+			"--keep-going" | "-k" => opts.keep_going = true,
+			"--interpret" => opts.interpret = true,
+			"--force" => opts.force = true,
+			"--no-cache" => opts.no_cache = true,
+			"--werror" => opts.werror = true,
+			"--severity" => opts.severity = args.next(),
+			"--dry-run" | "-n" => opts.dry_run = true,
+			"--recursive" | "-r" => recursive = true,
+			"--all" => all = true,
+			"--confirm" => confirm = true,
+			"--respect-gitignore" => respect_gitignore = true,
+			"--entry" => entry_name = args.next(),
+			"--target" | "-t" => forced = args.next(),
+			"--target-triple" => opts.triple = args.next(),
+			"--bin" => opts.bin_name = args.next(),
+			"--profile" => opts.profile = args.next(),
+			"--std" => opts.std_version = args.next(),
+			"--output" | "-o" => opts.output = args.next(),
+			"--out-dir" => opts.out_dir = args.next(),
+			"--emit" => opts.emit = args.next(),
+			"--prefix" => opts.prefix = args.next().map(PathBuf::from),
+			"--input" => opts.input = args.next().map(PathBuf::from),
+			"--timeout" => match args.next().map(|secs| secs.parse::<u64>()) {
+				Some(Ok(secs)) => opts.timeout = Some(secs),
 				_ => {
-					if target.is_none()
-						&& (entry.starts_with("main.")
-							|| entry.starts_with("index.")
-							|| entry.starts_with("test."))
-					{
-						target = update_target(target, endings(entry));
+					failure!("==== --timeout expects a number of seconds");
+					process::exit(2);
+				}
+			},
+			"--directory" | "-C" => directory = args.next(),
+			"--env" => env.extend(args.next()),
+			"--env-file" => env_file = args.next(),
+			"--pre" => pre = args.next(),
+			"--post" => post = args.next(),
+			"--jobs" | "-j" => {
+				opts.jobs = match args.peek().and_then(|arg| arg.parse::<usize>().ok()) {
+					Some(count) => {
+						args.next();
+						Some(count)
 					}
+					None => Some(thread::available_parallelism().map_or(1, |count| count.get())),
 				}
 			}
+			"--quiet" | "-q" => QUIET.store(true, Ordering::Relaxed),
+			"--no-color" => color = false,
+			"--format" => match args.next().as_deref() {
+				Some("json") => json = true,
+				Some("text") => json = false,
+				format => {
+					failure!(
+						"==== Unknown format ({}), expected text or json",
+						format.unwrap_or_default()
+					);
+					process::exit(2);
+				}
+			},
+			"--" => break,
+			_ => continue,
 		}
 	}
+	opts.forwarded = args.collect();
 
-	if lint {
-		if let Some(ref target) = target {
-			println!("==== Build target ({})", target.get_filename().unwrap());
+	if json {
+		JSON.store(true, Ordering::Relaxed);
+		QUIET.store(true, Ordering::Relaxed);
+	}
+	let no_color = std::env::var_os("NO_COLOR").is_some_and(|val| !val.is_empty());
+	COLOR.store(
+		color && !no_color && std::io::stdout().is_terminal(),
+		Ordering::Relaxed,
+	);
 
-			let mut command = match target {
-				Target::Make => {
-					let mut command = Command::new("make");
-					if release {
-						command.arg("lint");
-					}
-					command
-				}
-				Target::Cargo => {
-					let mut command = Command::new("cargo");
-					command.arg("fmt");
-					command
-				}
+	if let Some(ref version) = opts.std_version {
+		if !STD_VERSIONS.contains(&version.as_str()) {
+			failure!("==== Unknown language standard ({})", version);
+			process::exit(2);
+		}
+	}
 
-				Target::Cpp(ref file) => {
-					let mut command = Command::new("g++");
-					command.arg(file);
-					command.arg("-o");
-					command.arg(target.get_binary().unwrap());
-					if release {
-						command.arg("-O3");
-					}
-					command
-				}
-				Target::C(ref file) => {
-					let mut command = Command::new("gcc");
-					command.arg(file);
-					command.arg("-o");
-					command.arg(target.get_binary().unwrap());
-					if release {
-						command.arg("-O3");
-					}
-					command
-				}
-				Target::Rust(ref file) => {
-					let mut command = Command::new("rustc");
-					command.arg(file);
-					command
-				}
-				Target::Js(ref file) => {
-					let mut command = Command::new("eslint");
-					command.arg("--env").arg("es6").arg(file);
-					command
-				}
-				Target::Lua(ref file) => {
-					let mut command = Command::new("luacheck");
-					command.arg("-q").arg(file);
-					command
-				}
-				Target::Bash(ref file) => {
-					let mut command = Command::new("shellcheck");
-					command.arg("--norc").arg("--severity=style").arg(file);
-					command
-				}
-			};
+	if let Some(ref level) = opts.severity {
+		if !SEVERITIES.contains(&level.as_str()) {
+			failure!(
+				"==== Unknown severity ({}), expected one of {}",
+				level,
+				SEVERITIES.join(", ")
+			);
+			process::exit(2);
+		}
+	}
 
-			let child = command.spawn();
-			if let Ok(mut child) = child {
-				let ret = child
-					.wait()
-					.map_or(127, |code| code.code().expect("==== Linting terminated"));
+	if let Some(ref dir) = directory {
+		if let Err(err) = std::env::set_current_dir(dir) {
+			failure!("==== Cannot change to directory {} ({})", dir, err);
+			process::exit(2);
+		}
+	}
 
-				if target.handle_build_result(ret, None) {
-					println!("==== Linting Done");
-				} else {
-					println!("==== Linting Failed [{}]", ret);
-				}
-			} else {
-				println!("==== Failed to run lint command")
+	if let Some(ref kind) = init {
+		if let Err(err) = init::init(kind) {
+			failure!("==== Init Failed ({})", err);
+			process::exit(2);
+		}
+	}
+
+	if let Some(ref path) = env_file {
+		match std::fs::read_to_string(path) {
+			Ok(content) => opts.env.extend(
+				content
+					.lines()
+					.map(str::trim)
+					.filter(|line| !line.is_empty() && !line.starts_with('#'))
+					.filter_map(env_pair),
+			),
+			Err(err) => {
+				failure!("==== Could not read {} ({})", path, err);
+				process::exit(2);
+			}
+		}
+	}
+	for pair in env {
+		match env_pair(&pair) {
+			Some(pair) => opts.env.push(pair),
+			None => {
+				failure!("==== Invalid --env {}, expected KEY=VALUE", pair);
+				process::exit(2);
 			}
-		} else {
-			println!("==== No lint target found");
 		}
 	}
-	if build || release {
-		if let Some(ref target) = target {
-			println!("==== Build target ({})", target.get_filename().unwrap());
 
-			let mut command = match target {
-				Target::Make => {
-					let mut command = Command::new("make");
-					if release {
-						command.arg("release");
-					}
-					command
-				}
-				Target::Cargo => {
-					let mut command = Command::new("cargo");
-					command.arg("build");
-					if release {
-						command.arg("--release");
-					}
-					command
-				}
+	opts.config = match Config::load() {
+		Ok(config) => config,
+		Err(err) => {
+			failure!("==== Invalid {}\n{}", config::FILE_NAME, err);
+			process::exit(2);
+		}
+	};
 
-				Target::Cpp(ref file) => {
-					let mut command = Command::new("g++");
-					command.arg(file);
-					command.arg("-o");
-					command.arg(target.get_binary().unwrap());
-					if release {
-						command.arg("-O3");
-					}
-					command
-				}
-				Target::C(ref file) => {
-					let mut command = Command::new("gcc");
-					command.arg(file);
-					command.arg("-o");
-					command.arg(target.get_binary().unwrap());
-					if release {
-						command.arg("-O3");
-					}
-					command
-				}
-				Target::Rust(ref file) => {
-					let mut command = Command::new("rustc");
-					command.arg(file);
-					command
-				}
-				Target::Js(ref file) => {
-					let mut command = Command::new("eslint");
-					command.arg("--env").arg("es6").arg(file);
-					command
-				}
-				Target::Lua(ref file) => {
-					let mut command = Command::new("luacheck");
-					command.arg("-q").arg(file);
-					command
-				}
-				Target::Bash(ref file) => {
-					let mut command = Command::new("shellcheck");
-					command.arg("--norc").arg("--severity=warning").arg(file);
-					command
-				}
-			};
+	let mut prefixes: Vec<String> = PREFIXES.iter().map(|prefix| prefix.to_string()).collect();
+	if let Some(ref name) = entry_name {
+		if Path::new(name).is_file() {
+			target = endings(name);
+		} else {
+			prefixes.push(format!("{}.", name));
+		}
+	}
 
-			let child = command.spawn();
-			if let Ok(mut child) = child {
-				let ret = child
-					.wait()
-					.map_or(127, |code| code.code().expect("==== Build terminated"));
+	if let Some(ref line) = pre {
+		let code = hook("Pre", line, 0, opts.dry_run);
+		if code != 0 {
+			failure!("==== Pre hook failed [{}], aborting", code);
+			process::exit(code);
+		}
+	}
+	if all {
+		let code = build_all(&mut opts, &prefixes, respect_gitignore);
+		process::exit(post_hook(post.as_deref(), code, opts.dry_run));
+	}
 
-				if target.handle_build_result(ret, None) {
-					println!("==== Build Successfull");
-				} else {
-					run = false;
-					println!("==== Build Failed [{}]", ret);
-				}
+	if target.is_none() {
+		target = match detect(
+			Path::new("."),
+			&prefixes,
+			forced.as_deref(),
+			respect_gitignore,
+		) {
+			Ok(target) => target,
+			Err(err) => {
+				failure!("==== Cannot read current directory: {}", err);
+				process::exit(2);
+			}
+		};
+	}
+
+	if opts.verbose && entry_name.is_none() {
+		if let Some(ref found) = target {
+			let candidates = detect_candidates(
+				Path::new("."),
+				&prefixes,
+				forced.as_deref(),
+				respect_gitignore,
+			)
+			.unwrap_or_default();
+			let reason = |target: &Target| {
+				format!(
+					"{} (found {})",
+					target.kind(),
+					target.get_filename().unwrap_or_default()
+				)
+			};
+			let others: Vec<String> = candidates
+				.iter()
+				.filter(|candidate| *candidate != found)
+				.map(reason)
+				.collect();
+			if others.is_empty() {
+				status!("==== Detected {}", reason(found));
 			} else {
-				println!("==== Failed to run build command")
+				status!("==== Detected {} over {}", reason(found), others.join(", "));
 			}
-		} else {
-			println!("==== No build target found");
+		}
+	}
+
+	if target.is_none() && recursive {
+		target = find_entry(Path::new("."), 3, &prefixes);
+	}
+
+	if let Some(ref kind) = forced {
+		if target.as_ref().map(Target::kind) != Some(kind.as_str()) {
+			failure!("==== No {} target found", kind);
 			process::exit(2);
 		}
 	}
 
-	if run {
+	if !opts.features.is_empty() && target != Some(Target::Cargo) {
+		status!("==== Cargo features are ignored for non-cargo targets");
+	}
+	if opts.werror && !target.as_ref().is_some_and(Target::honours_werror) {
+		status!("==== --werror is ignored for this target");
+	}
+	if opts.profile.is_some() {
+		if target != Some(Target::Cargo) {
+			status!("==== --profile is ignored for non-cargo targets");
+		} else if opts.release {
+			status!("==== Both release and --profile given, using the profile");
+		}
+	}
+
+	if watch {
 		if let Some(ref target) = target {
-			let binary = target.get_binary();
-			if binary.is_none() {
-				println!("==== No target to run found {:?}", target);
-				process::exit(2);
-			}
-			let binary = binary.unwrap();
-			println!("==== Run target ({})", target.get_binary().unwrap());
+			header!("==== Watching target ({})", target.get_filename().unwrap());
+			// scripts are their own binary, their edits must not be ignored
+			let binary = resolve_binary(target, &opts)
+				.filter(|binary| Some(binary) != target.get_filename().as_ref());
+			watch::watch(binary, opts.out_dir.clone())?;
+		} else {
+			failure!("==== No target to watch found");
+			process::exit(2);
+		}
+	}
 
-			let mut command = match target {
-				Target::Make | Target::C(_) | Target::Cpp(_) | Target::Rust(_) => {
-					Command::new(format!("./{}", binary))
-				}
-				Target::Cargo => {
-					let mut command = Command::new("cargo");
-					command.arg("run");
-					if release {
-						command.arg("--release");
-					}
-					command
-				}
-				Target::Js(_) => {
-					let mut command = Command::new("node");
-					command.arg(format!("./{}", binary));
-					command
-				}
-				Target::Lua(_) => {
-					let mut command = Command::new("lua");
-					command.arg(format!("./{}", binary));
-					command
-				}
-				Target::Bash(_) => {
-					let mut command = Command::new("bash");
-					command.arg(format!("./{}", binary));
-					command
-				}
+	opts.build = build;
+	if opts.interpret {
+		if matches!(
+			target,
+			Some(Target::C(_) | Target::Rust(_) | Target::Swift(_) | Target::Crystal(_))
+		) {
+			if opts.build {
+				status!("==== Interpreting, skipping the build phase");
+			}
+			opts.build = false;
+		} else {
+			status!("==== --interpret is not supported for this target, building instead");
+			opts.interpret = false;
+			opts.build = opts.build || run;
+		}
+	}
+	if clean_first && opts.build {
+		clean = true;
+		// a restored binary would skip the rebuild the clean was asked for
+		opts.force = true;
+	}
+	let phases = [
+		(clean, Phase::Clean),
+		(lint, Phase::Lint),
+		(check, Phase::Check),
+		(opts.build, Phase::Build),
+		(test, Phase::Test),
+		(bench, Phase::Bench),
+		(doc, Phase::Doc),
+		(install, Phase::Install),
+		(run, Phase::Run),
+	];
+	if opts.release && !phases.iter().any(|(enabled, _)| *enabled) {
+		failure!("==== release only changes how phases run, add build, run, lint, ...");
+		process::exit(2);
+	}
+	let mut summary = Vec::new();
+	for (enabled, phase) in phases {
+		if !enabled {
+			continue;
+		}
+		if phase == Phase::Run && !run {
+			summary.push("run skipped".to_owned());
+			continue;
+		}
+		let Some(ref target) = target else {
+			failure!("==== No {} target found", phase.name());
+			exit_code = 2;
+			break;
+		};
+		if phase == Phase::Run && confirm && !opts.dry_run {
+			let binary = resolve_binary(target, &opts).unwrap_or_default();
+			if !ask(&format!("Run ./{}? [y/N] ", binary)) {
+				status!("==== Run skipped");
+				summary.push("run skipped".to_owned());
+				continue;
+			}
+		}
+		let start = Instant::now();
+		let code = run_phase(target, phase, &opts);
+		let command = take_command();
+		if json {
+			let result = PhaseResult {
+				phase: phase.name(),
+				target: target.kind(),
+				command,
+				exit_code: code,
+				duration_ms: start.elapsed().as_millis() as u64,
 			};
+			println!("{}", serde_json::to_string(&result)?);
+		}
+		summary.push(match (phase, code) {
+			(Phase::Run, code) => format!("run exited {}", code),
+			(phase, 0) => format!("{} OK", phase.name()),
+			(phase, code) => format!("{} failed [{}]", phase.name(), code),
+		});
+		if code != 0 {
+			exit_code = code;
+			if phase == Phase::Build {
+				run = false;
+			}
+		}
+	}
 
-			let child = command.spawn();
-			if let Ok(mut child) = child {
-				let ret = child
-					.wait()
-					.map_or(127, |code| code.code().expect("==== Build terminated"));
+	exit_code = post_hook(post.as_deref(), exit_code, opts.dry_run);
 
-				println!("==== Run return code [{}]", ret);
-			} else {
-				println!("==== Failed to run programm");
-			}
+	if summary.len() > 1 {
+		if exit_code == 0 {
+			success!("==== Summary: {}", summary.join(", "));
 		} else {
-			println!("==== No target to run found");
-			process::exit(2);
+			failure!("==== Summary: {}", summary.join(", "));
 		}
 	}
 
+	if exit_code != 0 {
+		process::exit(exit_code);
+	}
 	Ok(())
 }
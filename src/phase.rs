@@ -0,0 +1,1956 @@
+use crate::cache;
+use crate::config::{Config, TargetConfig};
+use crate::{
+	cargo_bins, compiler, deno_entry, env_flags, env_or, gradle, has_command, json_stdout,
+	kill_tree, locate_binary, modules, npm_script, output_binary, print_command, record_command,
+	remove_artifact, resolve_binary, run_step, sources, spawn_failed, status_code, up_to_date,
+	uses_out_dir, BuildOutcome, Target, OUTPUT_DIRS,
+};
+use std::env::consts::EXE_SUFFIX;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStderr, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Phase {
+	Clean,
+	Lint,
+	Check,
+	Build,
+	Test,
+	Bench,
+	Doc,
+	Install,
+	Run,
+}
+
+impl Phase {
+	pub fn name(self) -> &'static str {
+		match self {
+			Phase::Clean => "clean",
+			Phase::Lint => "lint",
+			Phase::Check => "check",
+			Phase::Build => "build",
+			Phase::Test => "test",
+			Phase::Bench => "bench",
+			Phase::Doc => "doc",
+			Phase::Install => "install",
+			Phase::Run => "run",
+		}
+	}
+}
+
+/// Everything a phase needs besides the target, filled in from the command line.
+#[derive(Default, Debug)]
+pub struct Opts {
+	pub verbose: bool,
+	pub dry_run: bool,
+	pub release: bool,
+	pub build: bool,
+	pub fmt: bool,
+	pub fix: bool,
+	pub keep_going: bool,
+	pub interpret: bool,
+	pub force: bool,
+	pub no_cache: bool,
+	pub werror: bool,
+	pub severity: Option<String>,
+	pub open: bool,
+	pub jobs: Option<usize>,
+	pub std_version: Option<String>,
+	pub output: Option<String>,
+	pub emit: Option<String>,
+	pub out_dir: Option<String>,
+	pub prefix: Option<PathBuf>,
+	pub input: Option<PathBuf>,
+	pub timeout: Option<u64>,
+	pub triple: Option<String>,
+	pub bench_name: Option<String>,
+	pub bin_name: Option<String>,
+	pub profile: Option<String>,
+	pub links: Vec<String>,
+	pub lib_paths: Vec<String>,
+	pub pkgs: Vec<String>,
+	pub frameworks: Vec<String>,
+	pub flags: Vec<String>,
+	pub features: Vec<String>,
+	pub forwarded: Vec<String>,
+	pub env: Vec<(String, String)>,
+	pub config: Config,
+}
+
+/// Compiles every file to its own object so one broken file does not hide errors in the others.
+fn compile_units(
+	compiler: &str,
+	files: &[String],
+	flags: &[String],
+	verbose: bool,
+	dry_run: bool,
+) -> Result<Vec<String>, i32> {
+	let mut objects = Vec::new();
+	let mut failed = 0;
+	for file in files {
+		let stem = file
+			.rsplit_once('.')
+			.map_or(file.as_str(), |(stem, _)| stem);
+		let object = format!("{}.o", stem);
+		let mut command = Command::new(compiler);
+		command
+			.arg("-c")
+			.arg(file)
+			.arg("-o")
+			.arg(&object)
+			.args(flags);
+		if run_step(command, verbose, dry_run) != 0 {
+			failed += 1;
+		}
+		objects.push(object);
+	}
+	if failed > 0 {
+		failure!("==== {} of {} files failed", failed, files.len());
+		return Err(1);
+	}
+	Ok(objects)
+}
+
+/// Prints, records and runs the main command of a phase, then reports `done` or `failed` and
+/// returns the exit code. `what` names the phase when the command cannot be started.
+fn execute(
+	mut command: Command,
+	target: &Target,
+	opts: &Opts,
+	what: &str,
+	done: &str,
+	failed: &str,
+) -> i32 {
+	if opts.verbose || opts.dry_run {
+		print_command(&command);
+	}
+	record_command(&command);
+	if opts.dry_run {
+		return 0;
+	}
+	json_stdout(&mut command);
+	let start = Instant::now();
+	match command.spawn() {
+		Ok(mut child) => {
+			let ret = child.wait().map_or(127, status_code);
+			let elapsed = start.elapsed().as_secs_f64();
+			match target.handle_build_result(ret, 0) {
+				BuildOutcome::Failed(ret) => {
+					failure!("==== {} [{}]", failed, ret);
+					ret
+				}
+				_ => {
+					success!("==== {} ({:.2}s)", done, elapsed);
+					0
+				}
+			}
+		}
+		Err(err) => {
+			spawn_failed(&command, &err, what);
+			127
+		}
+	}
+}
+
+/// Waits for child, killing it once `timeout` seconds have passed.
+fn wait_with_timeout(child: &mut Child, timeout: Option<u64>) -> i32 {
+	let Some(secs) = timeout else {
+		return child.wait().map_or(127, status_code);
+	};
+	let deadline = Instant::now() + Duration::from_secs(secs);
+	loop {
+		match child.try_wait() {
+			Ok(Some(status)) => return status_code(status),
+			Ok(None) if Instant::now() >= deadline => {
+				kill_tree(child);
+				failure!("==== Run timed out after {}s", secs);
+				return 124;
+			}
+			Ok(None) => thread::sleep(Duration::from_millis(20)),
+			Err(_) => return 127,
+		}
+	}
+}
+
+/// Echoes a compiler's stderr line by line as it is written and returns how many lines were
+/// warnings.
+fn echo_warnings(stderr: ChildStderr) -> usize {
+	let mut reader = BufReader::new(stderr);
+	let mut line = Vec::new();
+	let mut warnings = 0;
+	while reader
+		.read_until(b'\n', &mut line)
+		.is_ok_and(|read| read > 0)
+	{
+		let text = String::from_utf8_lossy(&line);
+		eprint!("{}", text);
+		// cargo ends with "warning: `name` (bin "name") generated 1 warning", not one more
+		let summary = text.starts_with("warning: `") && text.contains(" generated ");
+		if text.contains("warning:") && !summary {
+			warnings += 1;
+		}
+		line.clear();
+	}
+	warnings
+}
+
+/// False when `--emit` asks rustc for something other than the linked executable.
+fn emits_executable(opts: &Opts) -> bool {
+	opts.emit
+		.as_deref()
+		.is_none_or(|emit| emit.split(',').any(|kind| kind == "link"))
+}
+
+/// Runs a single phase for `target` and returns its exit code, 0 on success.
+pub fn run_phase(target: &Target, phase: Phase, opts: &Opts) -> i32 {
+	if let Err(err) = output_binary(target, opts) {
+		failure!(
+			"==== Could not read {} ({})",
+			target.get_filename().unwrap(),
+			err
+		);
+		return 2;
+	}
+	match phase {
+		Phase::Clean => clean(target, opts),
+		Phase::Lint => lint(target, opts),
+		Phase::Check => check(target, opts),
+		Phase::Build => build(target, opts),
+		Phase::Test => test(target, opts),
+		Phase::Bench => bench(target, opts),
+		Phase::Doc => doc(target, opts),
+		Phase::Install => install(target, opts),
+		Phase::Run => run(target, opts),
+	}
+}
+
+fn clean(target: &Target, opts: &Opts) -> i32 {
+	let dry_run = opts.dry_run;
+
+	header!("==== Clean target ({})", target.get_filename().unwrap());
+
+	let command = match target {
+		Target::Make => {
+			let mut command = Command::new("make");
+			command.arg("clean");
+			Some(command)
+		}
+		Target::Meson => {
+			let mut command = Command::new("meson");
+			command.arg("compile").arg("-C").arg("build").arg("--clean");
+			Some(command)
+		}
+		Target::Cargo => {
+			let mut command = Command::new("cargo");
+			command.arg("clean");
+			Some(command)
+		}
+		Target::CMake => {
+			let mut command = Command::new("cmake");
+			command
+				.arg("--build")
+				.arg("build")
+				.arg("--target")
+				.arg("clean");
+			Some(command)
+		}
+		Target::Dune => {
+			let mut command = Command::new("dune");
+			command.arg("clean");
+			Some(command)
+		}
+		Target::SwiftPackage => {
+			let mut command = Command::new("swift");
+			command.arg("package").arg("clean");
+			Some(command)
+		}
+		Target::Dub(_) => {
+			let mut command = Command::new("dub");
+			command.arg("clean");
+			Some(command)
+		}
+		Target::Gradle(_) => {
+			let mut command = gradle();
+			command.arg("clean");
+			Some(command)
+		}
+		Target::Mix => {
+			let mut command = Command::new("mix");
+			command.arg("clean");
+			Some(command)
+		}
+		Target::Stack => {
+			let mut command = Command::new("stack");
+			command.arg("clean");
+			Some(command)
+		}
+		Target::Cabal(_) => {
+			let mut command = Command::new("cabal");
+			command.arg("clean");
+			Some(command)
+		}
+		Target::Go(_) => {
+			let mut command = Command::new("go");
+			command.arg("clean");
+			Some(command)
+		}
+		Target::ZigBuild => {
+			remove_artifact("zig-out", dry_run);
+			remove_artifact(".zig-cache", dry_run);
+			None
+		}
+		Target::Cpp(_)
+		| Target::ObjC(_)
+		| Target::Vala(_)
+		| Target::Crystal(_)
+		| Target::D(_)
+		| Target::Kotlin(_)
+		| Target::Fortran(_)
+		| Target::Nim(_)
+		| Target::C(_)
+		| Target::Rust(_)
+		| Target::Zig(_)
+		| Target::TypeScript(_) => {
+			remove_artifact(&resolve_binary(target, opts).unwrap(), dry_run);
+			None
+		}
+		Target::Java(_) => {
+			remove_artifact(
+				&format!("{}.class", resolve_binary(target, opts).unwrap()),
+				dry_run,
+			);
+			None
+		}
+		Target::Asm(_) => {
+			let binary = resolve_binary(target, opts).unwrap();
+			remove_artifact(&format!("{}.o", binary), dry_run);
+			remove_artifact(&binary, dry_run);
+			None
+		}
+		Target::Ocaml(_) => {
+			let binary = resolve_binary(target, opts).unwrap();
+			let stem = binary.strip_suffix(EXE_SUFFIX).unwrap_or(&binary);
+			for ext in ["cmi", "cmx", "o"] {
+				remove_artifact(&format!("{}.{}", stem, ext), dry_run);
+			}
+			remove_artifact(&binary, dry_run);
+			None
+		}
+		Target::Haskell(_) => {
+			let binary = resolve_binary(target, opts).unwrap();
+			let stem = binary.strip_suffix(EXE_SUFFIX).unwrap_or(&binary);
+			for ext in ["hi", "o"] {
+				remove_artifact(&format!("{}.{}", stem, ext), dry_run);
+			}
+			remove_artifact(&binary, dry_run);
+			None
+		}
+		Target::Deno(_) => {
+			remove_artifact(&resolve_binary(target, opts).unwrap(), dry_run);
+			None
+		}
+		Target::Npm => {
+			status!("==== Nothing to clean");
+			None
+		}
+		Target::Js(_)
+		| Target::Lua(_)
+		| Target::Bash(_)
+		| Target::Python(_)
+		| Target::Perl(_)
+		| Target::R(_)
+		| Target::Elixir(_)
+		| Target::Swift(_)
+		| Target::Php(_)
+		| Target::Ruby(_) => {
+			status!("==== Nothing to clean");
+			None
+		}
+	};
+	if Path::new(cache::DIR).is_dir() {
+		remove_artifact(cache::DIR, dry_run);
+	}
+
+	command.map_or(0, |command| {
+		execute(command, target, opts, "clean", "Clean Done", "Clean Failed")
+	})
+}
+
+fn lint(target: &Target, opts: &Opts) -> i32 {
+	let release = opts.release;
+	let fmt = opts.fmt;
+	let fix = opts.fix;
+	let werror = opts.werror;
+	let severity = opts.severity.as_deref();
+	let features = &opts.features;
+	// anything below error fails the lint, like --werror
+	let deny_warnings = werror || severity.is_some_and(|level| level != "error");
+
+	header!("==== Build target ({})", target.get_filename().unwrap());
+
+	let settings = opts.config.get(target.kind());
+	let mut command = match target {
+		Target::Make => {
+			let mut command = Command::new("make");
+			command.arg("lint");
+			Some(command)
+		}
+		Target::Cargo => {
+			let mut command = Command::new("cargo");
+			if fmt {
+				command.arg("fmt");
+			} else {
+				command.arg("clippy");
+				command.args(features);
+				if fix {
+					command.arg("--fix").arg("--allow-dirty");
+				}
+				if release || deny_warnings {
+					command.arg("--").arg("-D").arg("warnings");
+				}
+			}
+			Some(command)
+		}
+		Target::Gradle(ref file) => {
+			let ktlint = std::fs::read_to_string(file).is_ok_and(|build| build.contains("ktlint"));
+			let mut command = gradle();
+			command.arg(if ktlint { "ktlintCheck" } else { "check" });
+			Some(command)
+		}
+		Target::Mix => {
+			let mut command = Command::new("mix");
+			command.arg("format");
+			if !fix {
+				command.arg("--check-formatted");
+			}
+			Some(command)
+		}
+		Target::CMake
+		| Target::Meson
+		| Target::Stack
+		| Target::Cabal(_)
+		| Target::Asm(_)
+		| Target::Fortran(_)
+		| Target::D(_)
+		| Target::Vala(_)
+		| Target::ObjC(_) => {
+			status!("==== No linter for {}, skipping", target.kind());
+			None
+		}
+		Target::Deno(_) => {
+			let mut command = Command::new("deno");
+			command.arg("lint");
+			if fix {
+				command.arg("--fix");
+			}
+			Some(command)
+		}
+		Target::Npm => {
+			let command = npm_script("lint");
+			if command.is_none() {
+				status!("==== No lint script in package.json, skipping");
+			}
+			command
+		}
+		Target::Dune => {
+			let mut command = Command::new("dune");
+			command.arg("build").arg("@fmt");
+			if fix {
+				command.arg("--auto-promote");
+			}
+			Some(command)
+		}
+		Target::SwiftPackage => {
+			let mut command = Command::new("swiftlint");
+			if fix {
+				command.arg("--fix");
+			}
+			Some(command)
+		}
+		Target::Dub(_) => {
+			let mut command = Command::new("dub");
+			command.arg("lint");
+			Some(command)
+		}
+		Target::Go(_) if fix => {
+			let mut command = Command::new("gofmt");
+			command.arg("-w").arg(".");
+			Some(command)
+		}
+		Target::Go(_) => {
+			let mut command = Command::new("go");
+			command.arg("vet").arg("./...");
+			Some(command)
+		}
+
+		Target::Cpp(ref file) => {
+			let mut command = Command::new(compiler(settings, "CXX", "g++"));
+			command.arg(file);
+			command.arg("-o");
+			command.arg(resolve_binary(target, opts).unwrap());
+			if release {
+				command.arg("-O3");
+			}
+			if werror {
+				command.arg("-Werror");
+			}
+			Some(command)
+		}
+		Target::C(ref file) => {
+			let mut command = Command::new(compiler(settings, "CC", "gcc"));
+			command.arg(file);
+			command.arg("-o");
+			command.arg(resolve_binary(target, opts).unwrap());
+			if release {
+				command.arg("-O3");
+			}
+			if werror {
+				command.arg("-Werror");
+			}
+			Some(command)
+		}
+		Target::Rust(ref file) if fix => {
+			let mut command = Command::new("rustfmt");
+			command.arg(file);
+			Some(command)
+		}
+		Target::Rust(ref file) => {
+			let mut command = Command::new("rustc");
+			command.arg(file);
+			if werror {
+				command.arg("-D").arg("warnings");
+			}
+			Some(command)
+		}
+		Target::Js(ref file) | Target::TypeScript(ref file) => {
+			let mut command = Command::new("eslint");
+			command.arg("--env").arg("es6").arg(file);
+			if fix {
+				command.arg("--fix");
+			}
+			if deny_warnings {
+				command.arg("--max-warnings").arg("0");
+			} else if severity == Some("error") {
+				command.arg("--quiet");
+			}
+			Some(command)
+		}
+		Target::Lua(ref file) => {
+			let mut command = Command::new("luacheck");
+			command.arg("-q").arg(file);
+			Some(command)
+		}
+		Target::Bash(ref file) => {
+			if fix {
+				status!("==== shellcheck has no fix mode, linting only");
+			}
+			let mut command = Command::new("shellcheck");
+			command
+				.arg("--norc")
+				.arg(format!("--severity={}", severity.unwrap_or("style")))
+				.arg(file);
+			Some(command)
+		}
+		Target::Python(ref file) => {
+			if has_command("ruff") {
+				let mut command = Command::new("ruff");
+				command.arg("check").arg(file);
+				if fix {
+					command.arg("--fix");
+				}
+				Some(command)
+			} else {
+				let mut command = Command::new("pyflakes");
+				command.arg(file);
+				Some(command)
+			}
+		}
+		Target::ZigBuild => {
+			let mut command = Command::new("zig");
+			command.arg("fmt").arg("--check").arg(".");
+			Some(command)
+		}
+		Target::Zig(ref file) => {
+			let mut command = Command::new("zig");
+			command.arg("fmt").arg("--check").arg(file);
+			Some(command)
+		}
+		Target::Ruby(ref file) => {
+			if has_command("rubocop") {
+				let mut command = Command::new("rubocop");
+				command.arg(file);
+				Some(command)
+			} else {
+				let mut command = Command::new("ruby");
+				command.arg("-wc").arg(file);
+				Some(command)
+			}
+		}
+		Target::Java(ref file) => {
+			if Path::new("checkstyle.xml").is_file() {
+				let mut command = Command::new("checkstyle");
+				command.arg("-c").arg("checkstyle.xml").arg(file);
+				Some(command)
+			} else {
+				status!("==== No checkstyle.xml for java, skipping");
+				None
+			}
+		}
+		Target::Nim(ref file) => {
+			let mut command = Command::new("nim");
+			command.arg("check").arg(file);
+			Some(command)
+		}
+		Target::Php(ref file) => {
+			if has_command("phpcs") {
+				let mut command = Command::new("phpcs");
+				command.arg(file);
+				Some(command)
+			} else {
+				let mut command = Command::new("php");
+				command.arg("-l").arg(file);
+				Some(command)
+			}
+		}
+		Target::Ocaml(ref file) => {
+			let mut command = Command::new("ocamlformat");
+			command.arg("--check").arg(file);
+			Some(command)
+		}
+		Target::Swift(ref file) => {
+			let mut command = Command::new("swiftlint");
+			command.arg("lint").arg(file);
+			Some(command)
+		}
+		Target::Kotlin(ref file) => {
+			let mut command = Command::new("ktlint");
+			if fix {
+				command.arg("--format");
+			}
+			command.arg(file);
+			Some(command)
+		}
+		Target::Haskell(ref file) => {
+			let mut command = Command::new("hlint");
+			command.arg(file);
+			Some(command)
+		}
+		Target::Crystal(ref file) => {
+			let mut command = Command::new("crystal");
+			command.arg("tool").arg("format").arg("--check").arg(file);
+			Some(command)
+		}
+		Target::Elixir(ref file) => {
+			let mut command = Command::new("mix");
+			command.arg("format");
+			if !fix {
+				command.arg("--check-formatted");
+			}
+			command.arg(file);
+			Some(command)
+		}
+		Target::R(ref file) => {
+			let mut command = Command::new("Rscript");
+			command.arg("-e").arg(format!(
+				"if (requireNamespace(\"lintr\", quietly = TRUE)) {{ lints <- lintr::lint(\"{}\"); print(lints); quit(status = as.integer(length(lints) > 0)) }} else message(\"lintr is not installed\")",
+				file
+			));
+			Some(command)
+		}
+		Target::Perl(ref file) => {
+			if has_command("perlcritic") {
+				let mut command = Command::new("perlcritic");
+				command.arg(file);
+				Some(command)
+			} else {
+				let mut command = Command::new("perl");
+				command.arg("-c").arg(file);
+				Some(command)
+			}
+		}
+	};
+
+	if let Some(custom) = settings.and_then(TargetConfig::lint_command) {
+		command = Some(custom);
+	}
+
+	command.map_or(0, |command| {
+		execute(
+			command,
+			target,
+			opts,
+			"lint",
+			"Linting Done",
+			"Linting Failed",
+		)
+	})
+}
+
+fn check(target: &Target, opts: &Opts) -> i32 {
+	let release = opts.release;
+	let std_version = &opts.std_version;
+	let features = &opts.features;
+	let mut exit_code = 0;
+
+	header!("==== Check target ({})", target.get_filename().unwrap());
+
+	let settings = opts.config.get(target.kind());
+	let command = match target {
+		Target::Make => {
+			let mut command = Command::new("make");
+			command.arg("check");
+			Some(command)
+		}
+		Target::Cargo => {
+			let mut command = Command::new("cargo");
+			command.arg("check");
+			command.args(features);
+			if release {
+				command.arg("--release");
+			}
+			Some(command)
+		}
+		Target::Deno(_) => {
+			let mut command = Command::new("deno");
+			command.arg("check").arg(deno_entry());
+			Some(command)
+		}
+		Target::Cpp(ref file) => {
+			let mut command = Command::new(compiler(settings, "CXX", "g++"));
+			command.args(sources(file, &[".cpp", ".cxx"]));
+			command.arg("-fsyntax-only");
+			command.args(env_flags("CXXFLAGS"));
+			if let Some(ref version) = std_version {
+				command.arg(format!("-std=c++{}", version));
+			}
+			Some(command)
+		}
+		Target::C(ref file) => {
+			let mut command = Command::new(compiler(settings, "CC", "gcc"));
+			command.args(sources(file, &[".c"]));
+			command.arg("-fsyntax-only");
+			command.args(env_flags("CFLAGS"));
+			if let Some(ref version) = std_version {
+				command.arg(format!("-std=c{}", version));
+			}
+			Some(command)
+		}
+		Target::Rust(ref file) => {
+			let mut command = Command::new("rustc");
+			command.arg(file);
+			command.arg("--emit=metadata");
+			command.arg("--out-dir").arg(std::env::temp_dir());
+			Some(command)
+		}
+		_ => {
+			status!("==== No check for {}, skipping", target.kind());
+			None
+		}
+	};
+
+	if let Some(command) = command {
+		exit_code = execute(command, target, opts, "check", "Check Done", "Check Failed");
+	}
+	exit_code
+}
+
+fn build(target: &Target, opts: &Opts) -> i32 {
+	let verbose = opts.verbose;
+	let dry_run = opts.dry_run;
+	let release = opts.release;
+	let jobs = opts.jobs;
+	let std_version = &opts.std_version;
+	let triple = &opts.triple;
+	let links = &opts.links;
+	let keep_going = opts.keep_going;
+	let werror = opts.werror;
+	let lib_paths = &opts.lib_paths;
+	let profile = &opts.profile;
+	let features = &opts.features;
+	let mut exit_code = 0;
+
+	header!("==== Build target ({})", target.get_filename().unwrap());
+
+	let settings = opts.config.get(target.kind());
+	// everything whose contents end up in the binary, headers and modules included
+	let inputs = match target {
+		Target::Cpp(ref file) => sources(file, &[".cpp", ".cxx", ".h", ".hpp", ".hh"]),
+		Target::C(ref file) => sources(file, &[".c", ".h"]),
+		Target::Rust(ref file) => modules(file),
+		_ => Vec::new(),
+	};
+	// only the plain compiler invocations produce a single binary from known inputs
+	let single_file = !inputs.is_empty()
+		&& opts.emit.is_none()
+		&& settings.and_then(TargetConfig::build_command).is_none();
+	if let Some(ref dir) = opts.out_dir {
+		if uses_out_dir(target) && !dry_run {
+			if let Err(err) = std::fs::create_dir_all(dir) {
+				failure!("==== Could not create {} ({})", dir, err);
+				return 1;
+			}
+		}
+	}
+	let mut command = match target {
+		Target::Make => {
+			let mut command = Command::new("make");
+			if release {
+				command.arg("release");
+			}
+			if let Some(jobs) = jobs {
+				command.arg("-j").arg(jobs.to_string());
+			}
+			Some(command)
+		}
+		Target::Cargo => {
+			let mut command = Command::new("cargo");
+			command.arg("build");
+			command.args(features);
+			if let Some(ref profile) = profile {
+				command.arg("--profile").arg(profile);
+			} else if release {
+				command.arg("--release");
+			}
+			if let Some(ref triple) = triple {
+				command.arg("--target").arg(triple);
+			}
+			if let Some(jobs) = jobs {
+				command.arg("--jobs").arg(jobs.to_string());
+			}
+			Some(command)
+		}
+		Target::CMake => {
+			let mut setup = Command::new("cmake");
+			setup.arg("-B").arg("build");
+			if release {
+				setup.arg("-DCMAKE_BUILD_TYPE=Release");
+			}
+			let ret = run_step(setup, verbose, dry_run);
+			if ret == 0 {
+				let mut command = Command::new("cmake");
+				command.arg("--build").arg("build");
+				if let Some(jobs) = jobs {
+					command.arg("--parallel").arg(jobs.to_string());
+				}
+				Some(command)
+			} else {
+				failure!("==== Configure Failed [{}]", ret);
+				exit_code = ret;
+				None
+			}
+		}
+		Target::Meson => {
+			let ret = if Path::new("build").is_dir() {
+				0
+			} else {
+				let mut setup = Command::new("meson");
+				setup.arg("setup").arg("build");
+				if release {
+					setup.arg("--buildtype=release");
+				}
+				run_step(setup, verbose, dry_run)
+			};
+			if ret == 0 {
+				let mut command = Command::new("meson");
+				command.arg("compile").arg("-C").arg("build");
+				if let Some(jobs) = jobs {
+					command.arg("-j").arg(jobs.to_string());
+				}
+				Some(command)
+			} else {
+				failure!("==== Configure Failed [{}]", ret);
+				exit_code = ret;
+				None
+			}
+		}
+		Target::Dune => {
+			let mut command = Command::new("dune");
+			command.arg("build");
+			if release {
+				command.arg("--profile").arg("release");
+			}
+			Some(command)
+		}
+		Target::SwiftPackage => {
+			let mut command = Command::new("swift");
+			command.arg("build");
+			if release {
+				command.arg("-c").arg("release");
+			}
+			if let Some(jobs) = jobs {
+				command.arg("-j").arg(jobs.to_string());
+			}
+			Some(command)
+		}
+		Target::Dub(_) => {
+			let mut command = Command::new("dub");
+			command.arg("build");
+			if release {
+				command.arg("--build=release");
+			}
+			Some(command)
+		}
+		Target::Gradle(_) => {
+			let mut command = gradle();
+			command.arg("build");
+			Some(command)
+		}
+		Target::Mix => {
+			let mut command = Command::new("mix");
+			command.arg("compile");
+			if release {
+				command.env("MIX_ENV", "prod");
+			}
+			Some(command)
+		}
+		Target::Stack => {
+			let mut command = Command::new("stack");
+			command.arg("build");
+			Some(command)
+		}
+		Target::Cabal(_) => {
+			let mut command = Command::new("cabal");
+			command.arg("build");
+			if release {
+				command.arg("--enable-optimization=2");
+			}
+			Some(command)
+		}
+		Target::Deno(_) => {
+			let mut command = Command::new("deno");
+			command.arg("compile").arg("-A");
+			command.arg("-o").arg(resolve_binary(target, opts).unwrap());
+			command.arg(deno_entry());
+			Some(command)
+		}
+		Target::Npm => {
+			let command = npm_script("build");
+			if command.is_none() {
+				status!("==== No build script in package.json, skipping");
+			}
+			command
+		}
+		Target::Go(_) => {
+			let mut command = Command::new("go");
+			command.arg("build");
+			if release {
+				command.args(["-ldflags", "-s -w"]);
+			}
+			Some(command)
+		}
+
+		Target::Cpp(ref file) | Target::C(ref file) if keep_going => {
+			let (cc, extensions, flags_var, std_prefix) = match target {
+				Target::Cpp(_) => (
+					compiler(settings, "CXX", "g++"),
+					&[".cpp", ".cxx"][..],
+					"CXXFLAGS",
+					"c++",
+				),
+				_ => (compiler(settings, "CC", "gcc"), &[".c"][..], "CFLAGS", "c"),
+			};
+			let mut flags = env_flags(flags_var);
+			if release {
+				flags.push("-O3".to_owned());
+			}
+			if werror {
+				flags.push("-Werror".to_owned());
+			}
+			if let Some(ref version) = std_version {
+				flags.push(format!("-std={}{}", std_prefix, version));
+			}
+			flags.extend(opts.flags.iter().cloned());
+			match compile_units(&cc, &sources(file, extensions), &flags, verbose, dry_run) {
+				Ok(objects) => {
+					let mut command = Command::new(cc);
+					command.args(objects);
+					command.arg("-o");
+					command.arg(resolve_binary(target, opts).unwrap());
+					command.args(lib_paths.iter().map(|dir| format!("-L{}", dir)));
+					command.args(links.iter().map(|lib| format!("-l{}", lib)));
+					Some(command)
+				}
+				Err(ret) => {
+					exit_code = ret;
+					None
+				}
+			}
+		}
+		Target::Cpp(ref file) => {
+			let mut command = Command::new(compiler(settings, "CXX", "g++"));
+			command.args(sources(file, &[".cpp", ".cxx"]));
+			command.arg("-o");
+			command.arg(resolve_binary(target, opts).unwrap());
+			if release {
+				command.arg("-O3");
+			}
+			if werror {
+				command.arg("-Werror");
+			}
+			command.args(env_flags("CXXFLAGS"));
+			command.args(lib_paths.iter().map(|dir| format!("-L{}", dir)));
+			command.args(links.iter().map(|lib| format!("-l{}", lib)));
+			if let Some(ref version) = std_version {
+				command.arg(format!("-std=c++{}", version));
+			}
+			Some(command)
+		}
+		Target::C(ref file) => {
+			let mut command = Command::new(compiler(settings, "CC", "gcc"));
+			command.args(sources(file, &[".c"]));
+			command.arg("-o");
+			command.arg(resolve_binary(target, opts).unwrap());
+			if release {
+				command.arg("-O3");
+			}
+			if werror {
+				command.arg("-Werror");
+			}
+			command.args(env_flags("CFLAGS"));
+			command.args(lib_paths.iter().map(|dir| format!("-L{}", dir)));
+			command.args(links.iter().map(|lib| format!("-l{}", lib)));
+			if let Some(ref version) = std_version {
+				command.arg(format!("-std=c{}", version));
+			}
+			Some(command)
+		}
+		Target::Rust(ref file) => {
+			let mut command = Command::new("rustc");
+			command.arg(file);
+			if emits_executable(opts) {
+				command.arg("-o");
+				command.arg(resolve_binary(target, opts).unwrap());
+			}
+			if let Some(ref emit) = opts.emit {
+				command.arg(format!("--emit={}", emit));
+			}
+			if release {
+				command.arg("-O");
+			}
+			if let Some(ref triple) = triple {
+				command.arg("--target").arg(triple);
+			}
+			if werror {
+				command.arg("-D").arg("warnings");
+			}
+			Some(command)
+		}
+		Target::TypeScript(ref file) => {
+			let mut command = Command::new("tsc");
+			command.arg(file);
+			Some(command)
+		}
+		Target::ZigBuild => {
+			let mut command = Command::new("zig");
+			command.arg("build");
+			if release {
+				command.arg("-Doptimize=ReleaseFast");
+			}
+			Some(command)
+		}
+		Target::Zig(ref file) => {
+			let mut command = Command::new("zig");
+			command.arg("build-exe").arg(file);
+			if release {
+				command.arg("-O").arg("ReleaseFast");
+			}
+			if opts.out_dir.is_some() {
+				command.arg(format!(
+					"-femit-bin={}",
+					resolve_binary(target, opts).unwrap()
+				));
+			}
+			Some(command)
+		}
+		Target::Java(ref file) => {
+			let mut command = Command::new("javac");
+			command.arg(file);
+			Some(command)
+		}
+		Target::Nim(ref file) => {
+			let mut command = Command::new("nim");
+			command.arg("c");
+			if release {
+				command.arg("-d:release");
+			}
+			if opts.out_dir.is_some() {
+				command.arg(format!("-o:{}", resolve_binary(target, opts).unwrap()));
+			}
+			command.arg(file);
+			Some(command)
+		}
+		Target::Asm(ref file) => {
+			let binary = resolve_binary(target, opts).unwrap();
+			let object = format!("{}.o", binary);
+			let mut setup = Command::new("nasm");
+			setup
+				.arg("-f")
+				.arg("elf64")
+				.arg(file)
+				.arg("-o")
+				.arg(&object);
+			let ret = run_step(setup, verbose, dry_run);
+			if ret == 0 {
+				let mut command = Command::new("ld");
+				command.arg("-o").arg(binary).arg(object);
+				Some(command)
+			} else {
+				failure!("==== Assemble Failed [{}]", ret);
+				exit_code = ret;
+				None
+			}
+		}
+		Target::Ocaml(ref file) => {
+			let mut command = Command::new("ocamlfind");
+			command.arg("ocamlopt").arg(file);
+			command.arg("-o");
+			command.arg(resolve_binary(target, opts).unwrap());
+			Some(command)
+		}
+		Target::Fortran(ref file) => {
+			let mut command = Command::new(compiler(settings, "FC", "gfortran"));
+			command.arg(file);
+			command.arg("-o");
+			command.arg(resolve_binary(target, opts).unwrap());
+			if release {
+				command.arg("-O3");
+			}
+			Some(command)
+		}
+		Target::Kotlin(ref file) => {
+			let mut command = Command::new("kotlinc");
+			command.arg(file).arg("-include-runtime");
+			command.arg("-d").arg(resolve_binary(target, opts).unwrap());
+			Some(command)
+		}
+		Target::D(ref file) => {
+			let default = if std::env::var_os("LDC").is_some() {
+				"ldc2"
+			} else {
+				"dmd"
+			};
+			let mut command = Command::new(compiler(settings, "DC", default));
+			command.arg(file);
+			command.arg(format!("-of={}", resolve_binary(target, opts).unwrap()));
+			if release {
+				command.arg("-O").arg("-release");
+			}
+			Some(command)
+		}
+		Target::Haskell(ref file) => {
+			let mut command = Command::new(compiler(settings, "GHC", "ghc"));
+			command.arg(file);
+			command.arg("-o");
+			command.arg(resolve_binary(target, opts).unwrap());
+			if release {
+				command.arg("-O2");
+			}
+			Some(command)
+		}
+		Target::Crystal(ref file) => {
+			let mut command = Command::new("crystal");
+			command.arg("build").arg(file);
+			command.arg("-o");
+			command.arg(resolve_binary(target, opts).unwrap());
+			if release {
+				command.arg("--release");
+			}
+			Some(command)
+		}
+		Target::Vala(ref file) => {
+			let mut command = Command::new("valac");
+			command.arg(file);
+			command.arg("-o");
+			command.arg(resolve_binary(target, opts).unwrap());
+			for pkg in &opts.pkgs {
+				command.arg("--pkg").arg(pkg);
+			}
+			if release {
+				command.arg("-X").arg("-O2");
+			}
+			Some(command)
+		}
+		Target::ObjC(ref file) => {
+			let mut command = Command::new(compiler(settings, "OBJC", "clang"));
+			if cfg!(target_os = "macos") {
+				command.arg("-framework").arg("Foundation");
+				for framework in &opts.frameworks {
+					command.arg("-framework").arg(framework);
+				}
+			} else if !opts.frameworks.is_empty() {
+				status!("==== Frameworks are only linked on macOS, ignoring --framework");
+			}
+			command.arg(file);
+			command.arg("-o");
+			command.arg(resolve_binary(target, opts).unwrap());
+			if release {
+				command.arg("-O3");
+			}
+			if werror {
+				command.arg("-Werror");
+			}
+			command.args(lib_paths.iter().map(|dir| format!("-L{}", dir)));
+			command.args(links.iter().map(|lib| format!("-l{}", lib)));
+			Some(command)
+		}
+		Target::Js(_)
+		| Target::Lua(_)
+		| Target::Bash(_)
+		| Target::Python(_)
+		| Target::Perl(_)
+		| Target::R(_)
+		| Target::Elixir(_)
+		| Target::Swift(_)
+		| Target::Php(_)
+		| Target::Ruby(_) => {
+			status!("==== Nothing to build for {}, skipping", target.kind());
+			None
+		}
+	};
+
+	if let Some(custom) = settings.and_then(TargetConfig::build_command) {
+		command = Some(custom);
+	}
+	if let Some(ref mut command) = command {
+		command.args(
+			settings
+				.map(|settings| settings.flags.as_slice())
+				.unwrap_or_default(),
+		);
+		command.args(&opts.flags);
+	}
+
+	if let Some(mut command) = command {
+		// also recorded when the build is skipped, so the result still names it
+		record_command(&command);
+		let stamp = if single_file && !keep_going {
+			cache::key(&command, &inputs)
+				.ok()
+				.map(|key| (key, resolve_binary(target, opts).unwrap()))
+		} else {
+			None
+		};
+		if let Some((ref key, ref binary)) = stamp.as_ref().filter(|_| !opts.force) {
+			// the binary only counts as current when the same command built it
+			if up_to_date(binary, &inputs) && cache::stamped(key, binary) {
+				status!("==== Up to date, skipping build");
+				return 0;
+			}
+			if !opts.no_cache && !dry_run && cache::restore(key, binary).unwrap_or(false) {
+				if let Err(err) = cache::stamp(key, binary) {
+					status!("==== Could not stamp {} ({})", binary, err);
+				}
+				success!("==== Restored {} from {}", binary, cache::DIR);
+				return 0;
+			}
+		}
+		if verbose || dry_run {
+			print_command(&command);
+		}
+		if !dry_run {
+			let start = Instant::now();
+			json_stdout(&mut command);
+			let child = command.stderr(Stdio::piped()).spawn();
+			if let Ok(mut child) = child {
+				let stderr = child.stderr.take().unwrap();
+				let warnings = thread::spawn(move || echo_warnings(stderr));
+				let ret = child.wait().map_or(127, status_code);
+				let warnings = warnings.join().unwrap_or(0);
+				let elapsed = start.elapsed().as_secs_f64();
+
+				match target.handle_build_result(ret, warnings) {
+					BuildOutcome::Success => success!("==== Build Successful ({:.2}s)", elapsed),
+					BuildOutcome::SuccessWithWarnings(warnings)
+						if opts.werror && target.honours_werror() =>
+					{
+						failure!("==== Build Failed ({} warnings with --werror)", warnings);
+						exit_code = 1;
+					}
+					BuildOutcome::SuccessWithWarnings(warnings) => success!(
+						"==== Build Successful ({} warnings, {:.2}s)",
+						warnings,
+						elapsed
+					),
+					BuildOutcome::Failed(ret) => {
+						failure!("==== Build Failed [{}]", ret);
+						exit_code = ret;
+					}
+				}
+				if let Some((key, binary)) = stamp.filter(|_| exit_code == 0) {
+					if let Err(err) = cache::stamp(&key, &binary) {
+						status!("==== Could not stamp {} ({})", binary, err);
+					}
+					if !opts.no_cache {
+						if let Err(err) = cache::store(&key, &binary) {
+							status!("==== Could not cache {} ({})", binary, err);
+						}
+					}
+				}
+			} else if let Err(err) = child {
+				exit_code = 127;
+				spawn_failed(&command, &err, "build");
+			}
+		}
+	}
+	exit_code
+}
+
+fn test(target: &Target, opts: &Opts) -> i32 {
+	let verbose = opts.verbose;
+	let dry_run = opts.dry_run;
+	let release = opts.release;
+	let features = &opts.features;
+	let mut exit_code = 0;
+
+	header!("==== Test target ({})", target.get_filename().unwrap());
+
+	let settings = opts.config.get(target.kind());
+	let command = match target {
+		Target::Make => {
+			let mut command = Command::new("make");
+			command.arg("test");
+			Some(command)
+		}
+		Target::Cargo => {
+			let mut command = Command::new("cargo");
+			command.arg("test");
+			command.args(features);
+			if release {
+				command.arg("--release");
+			}
+			Some(command)
+		}
+		Target::CMake => {
+			let mut command = Command::new("ctest");
+			command.arg("--test-dir").arg("build");
+			Some(command)
+		}
+		Target::Meson => {
+			let mut command = Command::new("meson");
+			command.arg("test").arg("-C").arg("build");
+			Some(command)
+		}
+		Target::Dune => {
+			let mut command = Command::new("dune");
+			command.arg("test");
+			Some(command)
+		}
+		Target::SwiftPackage => {
+			let mut command = Command::new("swift");
+			command.arg("test");
+			Some(command)
+		}
+		Target::Dub(_) => {
+			let mut command = Command::new("dub");
+			command.arg("test");
+			Some(command)
+		}
+		Target::Gradle(_) => {
+			let mut command = gradle();
+			command.arg("test");
+			Some(command)
+		}
+		Target::Mix => {
+			let mut command = Command::new("mix");
+			command.arg("test");
+			Some(command)
+		}
+		Target::Stack => {
+			let mut command = Command::new("stack");
+			command.arg("test");
+			Some(command)
+		}
+		Target::Cabal(_) => {
+			let mut command = Command::new("cabal");
+			command.arg("test");
+			Some(command)
+		}
+		Target::Deno(_) => {
+			let mut command = Command::new("deno");
+			command.arg("test").arg("-A");
+			Some(command)
+		}
+		Target::Npm => {
+			let command = npm_script("test");
+			if command.is_none() {
+				status!("==== No test script in package.json, skipping");
+			}
+			command
+		}
+		Target::Go(_) => {
+			let mut command = Command::new("go");
+			command.arg("test").arg("./...");
+			Some(command)
+		}
+		Target::ZigBuild => {
+			let mut command = Command::new("zig");
+			command.arg("build").arg("test");
+			Some(command)
+		}
+		Target::Zig(ref file) => {
+			let mut command = Command::new("zig");
+			command.arg("test").arg(file);
+			Some(command)
+		}
+		Target::C(_) | Target::Cpp(_) | Target::Rust(_) => {
+			let (entry, mut build) = match target {
+				Target::C(_) => ("test.c", Command::new(compiler(settings, "CC", "gcc"))),
+				Target::Cpp(_) => ("test.cpp", Command::new(compiler(settings, "CXX", "g++"))),
+				_ => {
+					let mut command = Command::new("rustc");
+					command.arg("--test");
+					("test.rs", command)
+				}
+			};
+			if Path::new(entry).is_file() {
+				build.arg(entry).arg("-o").arg("test");
+				let ret = run_step(build, verbose, dry_run);
+				if ret == 0 {
+					Some(Command::new("./test"))
+				} else {
+					failure!("==== Test Build Failed [{}]", ret);
+					exit_code = ret;
+					None
+				}
+			} else {
+				status!("==== No {} found, skipping", entry);
+				None
+			}
+		}
+		_ => {
+			status!(
+				"==== No test runner for {}, skipping",
+				target.get_filename().unwrap()
+			);
+			None
+		}
+	};
+
+	if let Some(command) = command {
+		exit_code = execute(
+			command,
+			target,
+			opts,
+			"test",
+			"Tests Passed",
+			"Tests Failed",
+		);
+	}
+	exit_code
+}
+
+fn bench(target: &Target, opts: &Opts) -> i32 {
+	let bench_name = &opts.bench_name;
+	let features = &opts.features;
+	let mut exit_code = 0;
+
+	header!("==== Bench target ({})", target.get_filename().unwrap());
+
+	let command = match target {
+		Target::Make => {
+			let mut command = Command::new("make");
+			command.arg("bench");
+			Some(command)
+		}
+		Target::Cargo => {
+			let mut command = Command::new("cargo");
+			command.arg("bench");
+			command.args(features);
+			if let Some(ref name) = bench_name {
+				command.arg(name);
+			}
+			Some(command)
+		}
+		Target::Go(_) => {
+			let mut command = Command::new("go");
+			command.arg("test").arg("-run=^$");
+			command.arg(format!("-bench={}", bench_name.as_deref().unwrap_or(".")));
+			command.arg("./...");
+			Some(command)
+		}
+		_ => {
+			status!("==== No benchmarks for {}, skipping", target.kind());
+			None
+		}
+	};
+
+	if let Some(command) = command {
+		exit_code = execute(command, target, opts, "bench", "Bench Done", "Bench Failed");
+	}
+	exit_code
+}
+
+fn doc(target: &Target, opts: &Opts) -> i32 {
+	let open = opts.open;
+	let features = &opts.features;
+	let mut exit_code = 0;
+
+	header!("==== Doc target ({})", target.get_filename().unwrap());
+
+	let command = match target {
+		Target::Make => {
+			let mut command = Command::new("make");
+			command.arg("doc");
+			Some(command)
+		}
+		Target::Cargo => {
+			let mut command = Command::new("cargo");
+			command.arg("doc");
+			command.args(features);
+			if open {
+				command.arg("--open");
+			}
+			Some(command)
+		}
+		Target::CMake | Target::Cpp(_) | Target::C(_) if Path::new("Doxyfile").is_file() => {
+			Some(Command::new("doxygen"))
+		}
+		_ => {
+			status!("==== No documentation tool for {}, skipping", target.kind());
+			None
+		}
+	};
+
+	if let Some(command) = command {
+		exit_code = execute(command, target, opts, "doc", "Doc Done", "Doc Failed");
+	}
+	exit_code
+}
+
+fn install(target: &Target, opts: &Opts) -> i32 {
+	let dry_run = opts.dry_run;
+	let prefix = &opts.prefix;
+	let features = &opts.features;
+	let mut exit_code = 0;
+
+	header!("==== Install target ({})", target.get_filename().unwrap());
+
+	let command = match target {
+		Target::Make => {
+			let mut command = Command::new("make");
+			command.arg("install");
+			Some(command)
+		}
+		Target::CMake => {
+			let mut command = Command::new("cmake");
+			command.arg("--install").arg("build");
+			if let Some(ref prefix) = prefix {
+				command.arg("--prefix").arg(prefix);
+			}
+			Some(command)
+		}
+		Target::Meson => {
+			let mut command = Command::new("meson");
+			command.arg("install").arg("-C").arg("build");
+			Some(command)
+		}
+		Target::Cargo => {
+			let mut command = Command::new("cargo");
+			command.arg("install").arg("--path").arg(".");
+			command.args(features);
+			Some(command)
+		}
+		Target::Go(_) => {
+			let mut command = Command::new("go");
+			command.arg("install");
+			Some(command)
+		}
+		Target::Cpp(_)
+		| Target::ObjC(_)
+		| Target::Vala(_)
+		| Target::Deno(_)
+		| Target::Crystal(_)
+		| Target::Haskell(_)
+		| Target::D(_)
+		| Target::Fortran(_)
+		| Target::Ocaml(_)
+		| Target::Asm(_)
+		| Target::Nim(_)
+		| Target::C(_)
+		| Target::Rust(_)
+		| Target::Zig(_) => {
+			let binary = resolve_binary(target, opts).unwrap();
+			let dir = prefix.clone().unwrap_or_else(|| {
+				PathBuf::from(env_or("HOME", "."))
+					.join(".local")
+					.join("bin")
+			});
+			let dest = dir.join(Path::new(&binary).file_name().unwrap());
+			if dry_run {
+				echo!("==== Would copy {} to {}", binary, dest.display());
+			} else if let Err(err) =
+				std::fs::create_dir_all(&dir).and_then(|_| std::fs::copy(&binary, &dest))
+			{
+				failure!("==== Install Failed ({})", err);
+				exit_code = 1;
+			} else {
+				success!("==== Installed {} to {}", binary, dest.display());
+			}
+			None
+		}
+		_ => {
+			status!("==== Nothing to install for {}, skipping", target.kind());
+			None
+		}
+	};
+
+	if let Some(command) = command {
+		exit_code = execute(
+			command,
+			target,
+			opts,
+			"install",
+			"Install Done",
+			"Install Failed",
+		);
+	}
+	exit_code
+}
+
+fn run(target: &Target, opts: &Opts) -> i32 {
+	let verbose = opts.verbose;
+	let dry_run = opts.dry_run;
+	let build = opts.build;
+	let release = opts.release;
+	let triple = &opts.triple;
+	let bin_name = &opts.bin_name;
+	let profile = &opts.profile;
+	let features = &opts.features;
+	let forwarded = &opts.forwarded;
+	let links = &opts.links;
+	let lib_paths = &opts.lib_paths;
+	let env = &opts.env;
+	let mut exit_code = 0;
+
+	if matches!(target, Target::Rust(_)) && !emits_executable(opts) {
+		status!(
+			"==== Nothing to run for --emit={}, skipping",
+			opts.emit.as_deref().unwrap_or_default()
+		);
+		return 0;
+	}
+	let no_bins = *target == Target::Cargo && cargo_bins().is_ok_and(|bins| bins.is_empty());
+	let binary = match (target, bin_name) {
+		(Target::Cargo, Some(name)) => Some(name.clone()),
+		// a workspace root has no binary of its own, `cargo run` picks the default member
+		(Target::Cargo, None) if no_bins && triple.is_none() => target.get_filename(),
+		// `npm start` needs no package name
+		(Target::Npm, _) => resolve_binary(target, opts).or_else(|| target.get_filename()),
+		_ => resolve_binary(target, opts),
+	};
+	if binary.is_none() {
+		if no_bins {
+			failure!("==== No binary found in Cargo.toml, select one with --bin");
+		} else if *target == Target::Cargo {
+			failure!(
+				"==== Multiple binaries found ({}), select one with --bin",
+				cargo_bins().unwrap_or_default().join(", ")
+			);
+		} else {
+			failure!("==== No target to run found {:?}", target);
+		}
+		return 2;
+	}
+	let binary = binary.unwrap();
+	header!("==== Run target ({})", binary);
+
+	let settings = opts.config.get(target.kind());
+	let mut command = match target {
+		Target::C(ref file) if opts.interpret => {
+			let mut command = Command::new("tcc");
+			command.args(lib_paths.iter().map(|dir| format!("-L{}", dir)));
+			command.args(links.iter().map(|lib| format!("-l{}", lib)));
+			command.args(
+				sources(file, &[".c"])
+					.into_iter()
+					.filter(|source| source != file),
+			);
+			command.arg("-run").arg(file);
+			command
+		}
+		Target::Rust(ref file) if opts.interpret => {
+			let mut command = Command::new("rust-script");
+			command.arg(file);
+			if !forwarded.is_empty() {
+				command.arg("--");
+			}
+			command
+		}
+		Target::Crystal(ref file) if !build => {
+			let mut command = Command::new("crystal");
+			command.arg("run").arg(file);
+			if release {
+				command.arg("--release");
+			}
+			if !forwarded.is_empty() {
+				command.arg("--");
+			}
+			command
+		}
+		Target::Make
+		| Target::C(_)
+		| Target::ObjC(_)
+		| Target::Vala(_)
+		| Target::Crystal(_)
+		| Target::Haskell(_)
+		| Target::D(_)
+		| Target::Fortran(_)
+		| Target::Ocaml(_)
+		| Target::Asm(_)
+		| Target::Nim(_)
+		| Target::Cpp(_)
+		| Target::Rust(_)
+		| Target::Zig(_) => match locate_binary(&binary) {
+			Some(path) => Command::new(path),
+			None if dry_run => Command::new(Path::new(".").join(&binary)),
+			None => {
+				failure!(
+					"==== Could not find {} (looked in {})",
+					binary,
+					OUTPUT_DIRS.join(", ")
+				);
+				return 2;
+			}
+		},
+		Target::CMake | Target::Meson => Command::new(
+			Path::new(".")
+				.join("build")
+				.join(format!("{}{}", binary, EXE_SUFFIX)),
+		),
+		Target::Cargo => {
+			if let Some(ref triple) = triple {
+				let profile = match profile.as_deref() {
+					Some("dev") => "debug",
+					Some(profile) => profile,
+					None if release => "release",
+					None => "debug",
+				};
+				Command::new(
+					Path::new(".")
+						.join("target")
+						.join(triple)
+						.join(profile)
+						.join(format!("{}{}", binary, EXE_SUFFIX)),
+				)
+			} else {
+				let mut command = Command::new("cargo");
+				command.arg("run");
+				command.args(features);
+				if let Some(ref name) = bin_name {
+					command.arg("--bin").arg(name);
+				}
+				if let Some(ref profile) = profile {
+					command.arg("--profile").arg(profile);
+				} else if release {
+					command.arg("--release");
+				}
+				if !forwarded.is_empty() {
+					command.arg("--");
+				}
+				command
+			}
+		}
+		Target::Dune => {
+			let mut command = Command::new("dune");
+			command.arg("exec");
+			if release {
+				command.arg("--profile").arg("release");
+			}
+			command.arg(&binary);
+			if !forwarded.is_empty() {
+				command.arg("--");
+			}
+			command
+		}
+		Target::SwiftPackage => {
+			let mut command = Command::new("swift");
+			command.arg("run");
+			if release {
+				command.arg("-c").arg("release");
+			}
+			command.arg(&binary);
+			command
+		}
+		Target::Dub(_) => {
+			let mut command = Command::new("dub");
+			command.arg("run");
+			if release {
+				command.arg("--build=release");
+			}
+			if !forwarded.is_empty() {
+				command.arg("--");
+			}
+			command
+		}
+		Target::Gradle(_) => {
+			let mut command = gradle();
+			command.arg("run");
+			command
+		}
+		Target::Mix => {
+			let mut command = Command::new("mix");
+			command.arg("run");
+			if release {
+				command.env("MIX_ENV", "prod");
+			}
+			command
+		}
+		Target::Stack | Target::Cabal(_) => {
+			let mut command = Command::new(if *target == Target::Stack {
+				"stack"
+			} else {
+				"cabal"
+			});
+			command.arg("run");
+			if !forwarded.is_empty() {
+				command.arg("--");
+			}
+			command
+		}
+		Target::Deno(_) => {
+			let mut command = Command::new("deno");
+			command.arg("run").arg("-A").arg(deno_entry());
+			command
+		}
+		Target::Npm => {
+			let Some(mut command) = npm_script("start") else {
+				failure!("==== No start script in package.json");
+				return 2;
+			};
+			if !forwarded.is_empty() {
+				command.arg("--");
+			}
+			command
+		}
+		Target::Go(_) => {
+			let mut command = Command::new("go");
+			command.arg("run").arg(".");
+			command
+		}
+		Target::ZigBuild => {
+			let mut command = Command::new("zig");
+			command.arg("build").arg("run");
+			if release {
+				command.arg("-Doptimize=ReleaseFast");
+			}
+			if !forwarded.is_empty() {
+				command.arg("--");
+			}
+			command
+		}
+		Target::Js(_) | Target::TypeScript(_) => {
+			let mut command = Command::new("node");
+			command.arg(format!("./{}", binary));
+			command
+		}
+		Target::Lua(_) => {
+			let mut command = Command::new("lua");
+			command.arg(format!("./{}", binary));
+			command
+		}
+		Target::Bash(_) => {
+			let mut command = Command::new("bash");
+			command.arg(format!("./{}", binary));
+			command
+		}
+		Target::Python(_) => {
+			let mut command = Command::new("python3");
+			command.arg(format!("./{}", binary));
+			command
+		}
+		Target::Ruby(_) => {
+			let mut command = Command::new("ruby");
+			command.arg(format!("./{}", binary));
+			command
+		}
+		Target::Java(_) => {
+			let class = Path::new(&binary);
+			let mut command = Command::new("java");
+			if let Some(dir) = class.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+				command.arg("-cp").arg(dir);
+			}
+			command.arg(class.file_name().unwrap());
+			command
+		}
+		Target::Php(_) => {
+			let mut command = Command::new("php");
+			command.arg(format!("./{}", binary));
+			command
+		}
+		Target::Swift(_) => {
+			let mut command = Command::new("swift");
+			command.arg(format!("./{}", binary));
+			command
+		}
+		Target::Kotlin(_) => {
+			let mut command = Command::new("java");
+			command.arg("-jar").arg(&binary);
+			command
+		}
+		Target::Elixir(_) => {
+			let mut command = Command::new("elixir");
+			command.arg(format!("./{}", binary));
+			command
+		}
+		Target::R(_) => {
+			let mut command = Command::new("Rscript");
+			command.arg(format!("./{}", binary));
+			command
+		}
+		Target::Perl(_) => {
+			let mut command = Command::new("perl");
+			command.arg(format!("./{}", binary));
+			command
+		}
+	};
+
+	if let Some(custom) = settings.and_then(TargetConfig::run_command) {
+		command = custom;
+		command.args(forwarded);
+	} else if let Target::Gradle(_) = target {
+		// gradle takes the program arguments as a single option
+		if !forwarded.is_empty() {
+			command.arg(format!("--args={}", forwarded.join(" ")));
+		}
+	} else {
+		command.args(forwarded);
+	}
+	command.envs(env.iter().map(|(key, value)| (key, value)));
+
+	if verbose || dry_run {
+		print_command(&command);
+	}
+	record_command(&command);
+	if !dry_run {
+		if let Some(ref input) = opts.input {
+			match File::open(input) {
+				Ok(file) => {
+					command.stdin(Stdio::from(file));
+				}
+				Err(err) => {
+					failure!("==== Could not read {} ({})", input.display(), err);
+					return 2;
+				}
+			}
+		}
+		json_stdout(&mut command);
+		let start = Instant::now();
+		let child = command.spawn();
+		if let Ok(mut child) = child {
+			let ret = wait_with_timeout(&mut child, opts.timeout);
+			let elapsed = start.elapsed().as_secs_f64();
+
+			status!("==== Run return code [{}] ({:.2}s)", ret, elapsed);
+			exit_code = ret;
+		} else if let Err(err) = child {
+			exit_code = 127;
+			spawn_failed(&command, &err, "program");
+		}
+	}
+	exit_code
+}
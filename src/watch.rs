@@ -0,0 +1,101 @@
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::Path;
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+
+const IGNORED: [&str; 7] = [
+	"target",
+	".git",
+	"node_modules",
+	"build",
+	"zig-out",
+	".zig-cache",
+	crate::cache::DIR,
+];
+// object files and the like written next to the sources while building
+const OUTPUTS: [&str; 6] = ["o", "obj", "class", "hi", "cmi", "cmx"];
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+fn is_relevant(event: &Event, root: &Path, binary: Option<&str>, out_dir: Option<&Path>) -> bool {
+	if matches!(event.kind, EventKind::Access(_)) {
+		return false;
+	}
+	event.paths.iter().any(|path| {
+		let path = path.strip_prefix(root).unwrap_or(path);
+		let ignored = path
+			.components()
+			.next()
+			.and_then(|first| first.as_os_str().to_str())
+			.is_some_and(|first| IGNORED.contains(&first));
+		let output = path
+			.extension()
+			.and_then(|ext| ext.to_str())
+			.is_some_and(|ext| OUTPUTS.contains(&ext));
+		!ignored
+			&& !output
+			&& binary.is_none_or(|binary| path != Path::new(binary))
+			&& out_dir.is_none_or(|dir| !path.starts_with(dir))
+	})
+}
+
+pub fn watch(
+	binary: Option<String>,
+	out_dir: Option<String>,
+) -> Result<(), Box<dyn std::error::Error + 'static>> {
+	let mut args = Vec::new();
+	let mut forwarding = false;
+	let mut original = std::env::args().skip(1);
+	while let Some(arg) = original.next() {
+		if forwarding {
+			args.push(arg);
+			continue;
+		}
+		match arg.as_str() {
+			"watch" => {}
+			// the working directory has already been changed, the child inherits it
+			"--directory" | "-C" => {
+				original.next();
+			}
+			_ => {
+				forwarding = arg == "--";
+				args.push(arg);
+			}
+		}
+	}
+	if !args
+		.iter()
+		.take_while(|arg| *arg != "--")
+		.any(|arg| ["build", "--build", "check"].contains(&arg.as_str()))
+	{
+		args.insert(0, "build".to_owned());
+	}
+
+	let (tx, rx) = mpsc::channel();
+	let mut watcher = notify::recommended_watcher(tx)?;
+	watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+	let root = std::env::current_dir()?.canonicalize()?;
+	let exe = std::env::current_exe()?;
+	let out_dir = out_dir.map(|dir| {
+		let dir = root.join(dir);
+		dir.strip_prefix(&root)
+			.map(Path::to_path_buf)
+			.unwrap_or(dir)
+	});
+
+	loop {
+		let mut child = Command::new(&exe).args(&args).spawn()?;
+
+		loop {
+			if let Ok(event) = rx.recv()? {
+				if is_relevant(&event, &root, binary.as_deref(), out_dir.as_deref()) {
+					break;
+				}
+			}
+		}
+		while rx.recv_timeout(DEBOUNCE).is_ok() {}
+
+		crate::kill_tree(&mut child);
+		status!("==== Change detected, rebuilding");
+	}
+}
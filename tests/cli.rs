@@ -0,0 +1,120 @@
+//! Runs the builder binary against throwaway projects in the temp directory.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("builder-{}-{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	for (file, contents) in files {
+		std::fs::write(dir.join(file), contents).unwrap();
+	}
+	dir
+}
+
+fn builder(dir: &Path, args: &[&str]) -> Output {
+	Command::new(env!("CARGO_BIN_EXE_builder"))
+		.current_dir(dir)
+		.args(args)
+		.output()
+		.unwrap()
+}
+
+/// The command of every phase that ran, taken from the `--format json` results.
+fn commands(output: &Output) -> Vec<String> {
+	String::from_utf8_lossy(&output.stdout)
+		.lines()
+		.map(|line| {
+			let result: serde_json::Value = serde_json::from_str(line).unwrap();
+			result["command"].as_str().unwrap_or_default().to_owned()
+		})
+		.collect()
+}
+
+#[test]
+fn rust_binary_is_named_after_the_file() {
+	let dir = project("index-rs", &[("index.rs", "fn main() {}\n")]);
+	let output = builder(&dir, &["build", "run", "--dry-run", "--format", "json"]);
+	assert!(output.status.success());
+	assert_eq!(
+		commands(&output),
+		["rustc index.rs -o index", "./index"].map(String::from)
+	);
+}
+
+#[cfg(unix)]
+#[test]
+fn build_of_scripts_runs_no_linter() {
+	use std::os::unix::fs::PermissionsExt;
+
+	for (name, source, linter) in [
+		("js", "main.js", "eslint"),
+		("lua", "main.lua", "luacheck"),
+		("bash", "main.sh", "shellcheck"),
+	] {
+		let dir = project(&format!("lint-{}", name), &[(source, "\n")]);
+		let fake = dir.join(linter);
+		std::fs::write(&fake, "#!/bin/sh\ntouch \"$(dirname \"$0\")/linted\"\n").unwrap();
+		std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+		let path = format!(
+			"{}:{}",
+			dir.display(),
+			std::env::var("PATH").unwrap_or_default()
+		);
+
+		let output = Command::new(env!("CARGO_BIN_EXE_builder"))
+			.current_dir(&dir)
+			.env("PATH", path)
+			.arg("build")
+			.output()
+			.unwrap();
+		assert!(output.status.success(), "{} build failed", name);
+		assert!(
+			!dir.join("linted").exists(),
+			"{} build ran {}",
+			name,
+			linter
+		);
+	}
+}
+
+#[test]
+fn makefile_lint_runs_the_lint_target() {
+	let dir = project(
+		"make-lint",
+		&[("Makefile", "all:\n\ttrue\n\nlint:\n\ttrue\n")],
+	);
+	for args in [&["lint"][..], &["lint", "release"]] {
+		let output = builder(&dir, &[args, &["--dry-run", "--format", "json"]].concat());
+		assert!(output.status.success());
+		assert_eq!(commands(&output), ["make lint"]);
+	}
+}
+
+#[test]
+fn cflags_are_passed_to_the_compiler() {
+	let dir = project("cflags", &[("main.c", "int main(void) { return 0; }\n")]);
+	let output = Command::new(env!("CARGO_BIN_EXE_builder"))
+		.current_dir(&dir)
+		.env("CFLAGS", " -Wall  -Wextra ")
+		.env_remove("CC")
+		.args(["build", "--dry-run", "--format", "json"])
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+	let command = &commands(&output)[0];
+	assert!(command.contains(" -Wall -Wextra"), "{}", command);
+}
+
+#[test]
+fn release_alone_runs_no_phase() {
+	let dir = project("release", &[("main.c", "int main(void) { return 0; }\n")]);
+	let output = builder(&dir, &["release"]);
+	assert_eq!(output.status.code(), Some(2));
+	assert!(!dir.join("main").exists());
+
+	let output = builder(&dir, &["release", "--format", "json"]);
+	assert_eq!(output.status.code(), Some(2));
+	assert!(commands(&output).is_empty());
+}